@@ -2,7 +2,16 @@
 //!
 //! 用于管理 `~/.claude/settings.json` 中的 API 配置切换
 
+use std::{path::PathBuf, process::ExitCode};
+
 use clap::{Parser, Subcommand};
+use claude_code_config_rs::{
+    commands::{backup, proxy, vault},
+    config::paths::SettingsScope,
+    output::{self, OutputFormat},
+    proxy::DEFAULT_PORT,
+    shell::Shell,
+};
 
 /// CLI 参数解析
 #[derive(Parser, Debug)]
@@ -11,6 +20,14 @@ use clap::{Parser, Subcommand};
 #[command(version = "0.1.0")]
 #[command(about = "Claude Code 配置管理工具", long_about = None)]
 struct Args {
+    /// Claude 配置目录 (默认: `CLAUDE_CONFIG_DIR` 环境变量或 ~/.claude)
+    #[arg(long, global = true, value_name = "DIR")]
+    config_dir: Option<PathBuf>,
+
+    /// 输出格式 (json 输出结构化结果，并隐藏提示信息)
+    #[arg(long, global = true, value_enum, default_value_t)]
+    output: OutputFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -23,16 +40,63 @@ enum Commands {
     /// 初始化配置管理
     Init,
     /// 查看当前配置和所有可用配置
-    List,
-    /// 交互式添加新的配置档案
+    List {
+        /// 用于检测当前配置的 settings 作用范围
+        #[arg(long, value_enum, default_value_t)]
+        scope: SettingsScope,
+    },
+    /// 查看配置档案详情
+    Show {
+        /// 配置名称
+        name: String,
+        /// 显示敏感字段的原文
+        #[arg(long)]
+        reveal: bool,
+        /// 用于比较的 settings 作用范围
+        #[arg(long, value_enum, default_value_t)]
+        scope: SettingsScope,
+    },
+    /// 添加新的配置档案 (未通过参数提供的信息会交互式询问)
     Add {
         /// 配置名称
         name: String,
+        /// Base URL
+        #[arg(long)]
+        base_url: Option<String>,
+        /// API Key (会出现在 shell 历史中，建议使用 --api-key-stdin)
+        #[arg(long, conflicts_with = "api_key_stdin")]
+        api_key: Option<String>,
+        /// 从 stdin 读取 API Key
+        #[arg(long)]
+        api_key_stdin: bool,
+        /// API Key Helper 命令
+        #[arg(long)]
+        helper: Option<String>,
+        /// 其他环境变量 (可重复指定，例如: --env `ANTHROPIC_MODEL=kimi-k2`)
+        #[arg(long = "env", value_name = "KEY=VALUE")]
+        env: Vec<String>,
+        /// 继承的父配置档案 (未指定的字段从父配置继承)
+        #[arg(long, value_name = "PROFILE")]
+        extends: Option<String>,
+    },
+    /// 修改配置档案 (未指定 --set/--unset 时在 $EDITOR 中编辑 JSON)
+    Edit {
+        /// 配置名称
+        name: String,
+        /// 设置字段 (可重复指定，例如: --set `ANTHROPIC_MODEL=kimi-k2`，--set apiKeyHelper=CMD，--set extends=PROFILE，--set fallback=A,B)
+        #[arg(long, value_name = "KEY=VALUE")]
+        set: Vec<String>,
+        /// 删除字段 (可重复指定，例如: --unset `ANTHROPIC_MODEL`，--unset apiKeyHelper，--unset extends，--unset fallback)
+        #[arg(long, value_name = "KEY")]
+        unset: Vec<String>,
     },
     /// 从当前 settings.json 导入配置档案
     Import {
         /// 配置名称
         name: String,
+        /// 读取的 settings 作用范围
+        #[arg(long, value_enum, default_value_t)]
+        scope: SettingsScope,
     },
     /// 删除指定配置档案
     #[command(alias = "del", alias = "rm")]
@@ -40,24 +104,298 @@ enum Commands {
         /// 配置名称
         name: String,
     },
+    /// 重命名配置档案
+    #[command(alias = "mv")]
+    Rename {
+        /// 原配置名称
+        old: String,
+        /// 新配置名称
+        new: String,
+        /// 新配置名称已存在时覆盖
+        #[arg(long)]
+        force: bool,
+    },
+    /// 复制配置档案
+    #[command(alias = "cp")]
+    Copy {
+        /// 源配置名称
+        src: String,
+        /// 目标配置名称
+        dst: String,
+        /// 目标配置名称已存在时覆盖
+        #[arg(long)]
+        force: bool,
+    },
+    /// 使用指定配置运行命令，不修改 settings.json
+    #[command(alias = "run")]
+    Exec {
+        /// 配置名称
+        name: String,
+        /// 要运行的命令及其参数
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "COMMAND"
+        )]
+        command: Vec<String>,
+    },
+    /// 输出设置配置环境变量的 shell 语句 (用法: eval "$(cccrs env <name>)")
+    Env {
+        /// 配置名称 (使用 --unset 时可省略，表示清除所有配置的环境变量)
+        #[arg(required_unless_present = "unset")]
+        name: Option<String>,
+        /// 目标 shell (默认根据 SHELL 环境变量检测)
+        #[arg(long, value_enum)]
+        shell: Option<Shell>,
+        /// 输出清除环境变量的语句
+        #[arg(long)]
+        unset: bool,
+    },
+    /// 输出 shell hook，进入包含 .cccrs / .cccrs.toml 的目录时自动设置对应配置的环境变量 (例如: eval "$(cccrs hook bash)")
+    Hook {
+        /// 目标 shell (bash、zsh 或 fish)
+        #[arg(value_enum)]
+        shell: Shell,
+        /// 输出当前目录需要执行的语句 (由 hook 调用)
+        #[arg(long, hide = true)]
+        apply: bool,
+    },
+    /// 管理 settings.json 备份
+    Backup {
+        /// 备份操作
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    /// 检查配置档案的端点是否可达、凭据是否有效
+    Test {
+        /// 配置名称
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+        /// 检查所有配置档案
+        #[arg(long)]
+        all: bool,
+        /// 请求超时时间（秒）
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// 测量配置档案端点的延迟 (p50 / p95) 和错误率并排名
+    Bench {
+        /// 配置名称 (默认测量所有配置档案)
+        names: Vec<String>,
+        /// 每个配置档案的请求次数
+        #[arg(short = 'n', long, default_value_t = 5)]
+        count: usize,
+        /// 请求超时时间（秒）
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+        /// 切换到最快的配置档案
+        #[arg(long = "use")]
+        use_best: bool,
+    },
+    /// 诊断配置切换不生效等常见问题
+    Doctor,
+    /// 检查包含 API Key 的文件权限 (配置文件、settings 及其备份应为 600)
+    Permissions {
+        /// 将可被其他用户访问的文件权限修改为 600
+        #[arg(long)]
+        fix: bool,
+    },
+    /// 管理加密存储 (口令从 `CCCRS_PASSPHRASE` 环境变量读取，未设置时交互式输入)
+    Vault {
+        /// 加密存储操作
+        #[command(subcommand)]
+        action: VaultAction,
+    },
+    /// 本地路由代理 (将 Claude Code 的请求转发到代理目标配置档案)
+    Proxy {
+        /// 代理操作
+        #[command(subcommand)]
+        action: ProxyAction,
+    },
     /// 切换到指定配置
     Use {
         /// 配置名称
         name: String,
+        /// 移除之前由 cccrs 写入、但目标配置未定义的字段
+        #[arg(long)]
+        clean: bool,
+        /// 写入的 settings 作用范围
+        #[arg(long, value_enum, default_value_t)]
+        scope: SettingsScope,
+        /// 依次检查配置档案及其备用配置 (fallback)，切换到第一个可用的配置
+        #[arg(long)]
+        auto: bool,
+        /// 检查端点的请求超时时间（秒）
+        #[arg(long, default_value_t = 10, requires = "auto")]
+        timeout: u64,
+    },
+}
+
+/// 备份子命令
+#[derive(Subcommand, Debug)]
+enum BackupAction {
+    /// 列出所有备份
+    #[command(alias = "ls")]
+    List,
+    /// 从备份恢复 settings.json（恢复前会先备份当前文件）
+    Restore {
+        /// 备份 ID，或 latest 表示最新备份
+        id: String,
+    },
+    /// 比较备份与当前 settings.json 的差异
+    Diff {
+        /// 备份 ID，或 latest 表示最新备份
+        id: String,
+    },
+    /// 清理旧备份
+    Prune {
+        /// 保留最新的 N 个备份
+        #[arg(long)]
+        keep: Option<usize>,
+        /// 删除早于指定时长的备份 (例如: 30d, 12h, 2w)
+        #[arg(long)]
+        older_than: Option<String>,
+    },
+    /// 查看或设置自动清理备份的保留策略
+    Retention {
+        /// 最多保留的备份数量 (0 表示不限制)
+        #[arg(long)]
+        keep: Option<usize>,
+        /// 备份最长保留时长 (例如: 30d，none 表示不限制)
+        #[arg(long)]
+        max_age: Option<String>,
     },
 }
 
-fn main() -> anyhow::Result<()> {
+/// 代理子命令
+#[derive(Subcommand, Debug)]
+enum ProxyAction {
+    /// 在 127.0.0.1 上启动代理 (前台运行，Ctrl+C 退出)
+    Start {
+        /// 监听端口 (0 表示自动选择空闲端口)
+        #[arg(long, default_value_t = DEFAULT_PORT)]
+        port: u16,
+        /// 启动前将指定配置设为代理目标
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// 切换代理目标，运行中的代理立即生效，不修改 settings.json
+    Switch {
+        /// 配置名称
+        name: String,
+    },
+}
+
+/// 加密存储子命令
+#[derive(Subcommand, Debug)]
+enum VaultAction {
+    /// 将配置文件中的明文敏感字段加密保存到加密存储
+    Encrypt,
+    /// 将加密存储中的敏感字段还原为明文，并删除加密存储
+    Decrypt,
+    /// 查看加密存储状态
+    Status,
+}
+
+fn main() -> anyhow::Result<ExitCode> {
     let args = Args::parse();
-    use claude_code_config_rs::commands::{add, import, init, install, list, remove, use_cmd};
+    output::set_format(args.output);
+    if let Some(dir) = args.config_dir {
+        let dir = std::path::absolute(&dir).unwrap_or(dir);
+        claude_code_config_rs::config::paths::set_config_dir_override(dir);
+    }
+    use claude_code_config_rs::commands::{
+        AddOptions, add, bench, copy, doctor, edit, env, exec, hook, import, init, install, list,
+        permissions, remove, rename, show, test_cmd, use_auto, use_cmd,
+    };
 
-    match args.command {
+    let result = match args.command {
         Commands::Install => install(),
         Commands::Init => init(),
-        Commands::List => list(),
-        Commands::Add { name } => add(&name),
-        Commands::Import { name } => import(&name),
+        Commands::List { scope } => list(scope),
+        Commands::Show {
+            name,
+            reveal,
+            scope,
+        } => show(&name, reveal, scope),
+        Commands::Add {
+            name,
+            base_url,
+            api_key,
+            api_key_stdin,
+            helper,
+            env,
+            extends,
+        } => add(
+            &name,
+            AddOptions {
+                base_url,
+                api_key,
+                api_key_stdin,
+                api_key_helper: helper,
+                env,
+                extends,
+            },
+        ),
+        Commands::Edit { name, set, unset } => edit(&name, &set, &unset),
+        Commands::Import { name, scope } => import(&name, scope),
         Commands::Remove { name } => remove(&name),
-        Commands::Use { name } => use_cmd(&name),
+        Commands::Rename { old, new, force } => rename(&old, &new, force),
+        Commands::Copy { src, dst, force } => copy(&src, &dst, force),
+        Commands::Env { name, shell, unset } => env(name.as_deref(), shell, unset),
+        Commands::Hook { shell, apply } => hook(shell, apply),
+        Commands::Backup { action } => match action {
+            BackupAction::List => backup::list(),
+            BackupAction::Restore { id } => backup::restore(&id),
+            BackupAction::Diff { id } => backup::diff(&id),
+            BackupAction::Prune { keep, older_than } => backup::prune(keep, older_than.as_deref()),
+            BackupAction::Retention { keep, max_age } => {
+                backup::retention(keep, max_age.as_deref())
+            }
+        },
+        Commands::Permissions { fix } => permissions(fix),
+        Commands::Proxy { action } => match action {
+            ProxyAction::Start { port, profile } => proxy::start(port, profile.as_deref()),
+            ProxyAction::Switch { name } => proxy::switch(&name),
+        },
+        Commands::Vault { action } => match action {
+            VaultAction::Encrypt => vault::encrypt(),
+            VaultAction::Decrypt => vault::decrypt(),
+            VaultAction::Status => vault::status(),
+        },
+        Commands::Use {
+            name,
+            clean,
+            scope,
+            auto: true,
+            timeout,
+        } => use_auto(&name, clean, scope, timeout),
+        Commands::Use {
+            name, clean, scope, ..
+        } => use_cmd(&name, clean, scope),
+        Commands::Exec { name, command } => return finish(exec(&name, &command)),
+        Commands::Bench {
+            names,
+            count,
+            timeout,
+            use_best,
+        } => return finish(bench(&names, count, timeout, use_best)),
+        Commands::Doctor => return finish(doctor()),
+        Commands::Test { name, timeout, .. } => return finish(test_cmd(name.as_deref(), timeout)),
+    };
+    finish(result.map(|()| ExitCode::SUCCESS))
+}
+
+/// 处理命令结果
+///
+/// JSON 模式下错误以 JSON 形式输出到 stdout，并以失败状态码退出
+fn finish(result: anyhow::Result<ExitCode>) -> anyhow::Result<ExitCode> {
+    match result {
+        Err(err) if output::is_json() => {
+            output::emit_error(&err);
+            Ok(ExitCode::FAILURE)
+        }
+        result => result,
     }
 }
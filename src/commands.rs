@@ -3,18 +3,43 @@
 //! 包含所有可用的子命令实现
 
 pub mod add;
+pub mod backup;
+pub mod bench;
+pub mod copy;
+pub mod doctor;
+pub mod edit;
+pub mod env;
+pub mod exec;
+pub mod hook;
 pub mod import;
 pub mod init;
 pub mod install;
 pub mod list;
+pub mod permissions;
+pub mod proxy;
 pub mod remove;
+pub mod rename;
+pub mod show;
+pub mod test_cmd;
 pub mod use_cmd;
+pub mod vault;
 
 // Re-export for easier access
-pub use add::execute as add;
+pub use add::{AddOptions, execute as add};
+pub use bench::execute as bench;
+pub use copy::execute as copy;
+pub use doctor::execute as doctor;
+pub use edit::execute as edit;
+pub use env::execute as env;
+pub use exec::execute as exec;
+pub use hook::execute as hook;
 pub use import::execute as import;
 pub use init::execute as init;
 pub use install::execute as install;
 pub use list::execute as list;
+pub use permissions::execute as permissions;
 pub use remove::execute as remove;
-pub use use_cmd::execute as use_cmd;
+pub use rename::execute as rename;
+pub use show::execute as show;
+pub use test_cmd::execute as test_cmd;
+pub use use_cmd::{execute as use_cmd, execute_auto as use_auto};
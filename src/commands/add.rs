@@ -1,94 +1,251 @@
 //! Add 命令
 //!
-//! 交互式添加新的配置档案
+//! 通过命令行参数或交互式输入添加新的配置档案
+
+use std::io::{IsTerminal, Read};
 
 use anyhow::Context;
 use dialoguer::{Confirm, Input};
 
 use crate::{
-    config::{CccConfig, EnvConfig, Profile},
-    output::{error, info, success},
+    config::{CccConfig, ConfigLock, EnvConfig, Profile},
+    error::{CccError, STDIN_NOT_TERMINAL},
+    output::{emit, error, info, is_json, success},
 };
 
-/// 交互式添加配置档案
+/// 通过命令行参数提供的配置信息
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    /// Base URL
+    pub base_url: Option<String>,
+    /// API Key
+    pub api_key: Option<String>,
+    /// 从 stdin 读取 API Key
+    pub api_key_stdin: bool,
+    /// API Key Helper 命令
+    pub api_key_helper: Option<String>,
+    /// 其他环境变量 (`KEY=VALUE`)
+    pub env: Vec<String>,
+    /// 继承的父配置档案名称
+    pub extends: Option<String>,
+}
+
+impl AddOptions {
+    /// 是否未通过参数提供任何配置信息
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.base_url.is_none()
+            && self.api_key.is_none()
+            && !self.api_key_stdin
+            && self.api_key_helper.is_none()
+            && self.env.is_empty()
+            && self.extends.is_none()
+    }
+}
+
+/// 添加配置档案
 ///
-/// 提示用户输入 Base URL、API Key 和可选的 API Key Helper
+/// 只提示输入参数中缺少的 Base URL 和 API Key（指定了 API Key Helper 时 API Key 可省略，
+/// 指定了父配置时两者均可省略并从父配置继承）；
+/// 未提供任何参数时进入完整的交互式流程。stdin 不是终端且缺少必要信息时返回错误
 ///
 /// # Errors
 ///
-/// 返回配置名称无效、配置已存在、用户取消等错误
-pub fn execute(name: &str) -> anyhow::Result<()> {
+/// 返回配置名称无效、配置已存在、缺少必要信息、用户取消等错误
+pub fn execute(name: &str, options: AddOptions) -> anyhow::Result<()> {
     // 验证配置名称
     if !crate::config::validate_profile_name(name) {
         error("配置名称只能包含字母、数字、下划线和连字符");
-        anyhow::bail!("无效的配置名称");
+        return Err(CccError::InvalidProfileName(name.to_string()).into());
     }
 
-    let mut config = CccConfig::load().context("加载配置失败")?;
+    let existing = CccConfig::load().context("加载配置失败")?;
+    if existing.has_profile(name) {
+        error(&format!("配置档案 '{}' 已存在", name));
+        return Err(CccError::ProfileExists(name.to_string()).into());
+    }
+    if let Some(parent) = &options.extends {
+        existing.resolve_profile(parent)?;
+    }
+
+    // 先解析参数中的环境变量，格式错误时尽早失败
+    let mut extra_env = Vec::with_capacity(options.env.len());
+    for assignment in &options.env {
+        let (key, value) = EnvConfig::parse_assignment(assignment).ok_or_else(|| {
+            anyhow::anyhow!("无效的环境变量 '{}': 格式应为 KEY=VALUE", assignment)
+        })?;
+        extra_env.push((key.to_string(), value.to_string()));
+    }
+
+    let api_key = if options.api_key_stdin {
+        Some(read_api_key_from_stdin()?)
+    } else {
+        options.api_key.clone()
+    };
+
+    // 从 stdin 读取 API Key 后 stdin 已不可用于交互
+    let interactive = !options.api_key_stdin && std::io::stdin().is_terminal();
+    let fully_interactive = interactive && options.is_empty();
+    let reason = if options.api_key_stdin {
+        "已使用 --api-key-stdin 从 stdin 读取 API Key"
+    } else {
+        STDIN_NOT_TERMINAL
+    };
+
+    if interactive && !is_json() {
+        println!("创建配置档案: {}", name);
+        println!();
+    }
+
+    let base_url = match options.base_url {
+        Some(base_url) => Some(base_url),
+        None if options.extends.is_some() => None,
+        None if interactive => Some(prompt_required(
+            "请输入 Base URL (例如: https://api.anthropic.com)",
+            "Base URL 不能为空",
+        )?),
+        None => {
+            return Err(CccError::MissingInput {
+                what: "Base URL",
+                reason,
+                flag: "--base-url",
+            }
+            .into());
+        }
+    };
+
+    let api_key = match api_key {
+        Some(api_key) => Some(api_key),
+        None if options.api_key_helper.is_some() || options.extends.is_some() => None,
+        None if interactive => Some(prompt_required(
+            "请输入 API Key (例如: sk-xxx)",
+            "API Key 不能为空",
+        )?),
+        None => {
+            return Err(CccError::MissingInput {
+                what: "API Key",
+                reason,
+                flag: "--api-key、--api-key-stdin 或 --helper",
+            }
+            .into());
+        }
+    };
+
+    let api_key_helper = if fully_interactive {
+        prompt_helper()?
+    } else {
+        options.api_key_helper
+    };
 
+    let mut env = EnvConfig::new(base_url, api_key);
+    for (key, value) in extra_env {
+        env.set(&key, value);
+    }
+    if fully_interactive {
+        prompt_extra_env(&mut env)?;
+    }
+
+    // 创建配置
+    let mut profile = Profile::new(api_key_helper, env);
+    profile.extends = options.extends;
+
+    // 交互期间配置可能已被其他进程修改，加锁后重新加载
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
     if config.has_profile(name) {
         error(&format!("配置档案 '{}' 已存在", name));
-        anyhow::bail!("配置档案已存在");
+        return Err(CccError::ProfileExists(name.to_string()).into());
+    }
+
+    config.insert_profile(name.to_string(), profile);
+    config.save().context("保存配置失败")?;
+
+    if is_json() {
+        emit("add", serde_json::json!({ "profile": name }));
+        return Ok(());
     }
 
-    println!("创建配置档案: {}", name);
     println!();
+    success(&format!("配置档案 '{}' 已创建", name));
+    info(&format!("提示: 使用 'ccc use {}' 切换到此配置", name));
 
-    // 交互式获取配置信息
-    let base_url: String = Input::with_theme(&dialoguer::theme::ColorfulTheme::default())
-        .with_prompt("请输入 Base URL (例如: https://api.anthropic.com)")
-        .validate_with(|input: &String| {
-            if input.is_empty() {
-                Err("Base URL 不能为空")
-            } else {
-                Ok(())
-            }
-        })
-        .interact()
-        .context("读取输入失败")?;
+    Ok(())
+}
 
-    let api_key: String = Input::with_theme(&dialoguer::theme::ColorfulTheme::default())
-        .with_prompt("请输入 API Key (例如: sk-xxx)")
+/// 从 stdin 读取 API Key，去除首尾空白
+fn read_api_key_from_stdin() -> anyhow::Result<String> {
+    let mut input = String::new();
+    std::io::stdin()
+        .read_to_string(&mut input)
+        .context("从 stdin 读取 API Key 失败")?;
+    let api_key = input.trim();
+    if api_key.is_empty() {
+        anyhow::bail!("从 stdin 读取的 API Key 为空");
+    }
+    Ok(api_key.to_string())
+}
+
+/// 提示输入不能为空的值
+fn prompt_required(prompt: &str, empty_message: &'static str) -> anyhow::Result<String> {
+    Input::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(prompt)
         .validate_with(|input: &String| {
             if input.is_empty() {
-                Err("API Key 不能为空")
+                Err(empty_message)
             } else {
                 Ok(())
             }
         })
         .interact()
-        .context("读取输入失败")?;
+        .context("读取输入失败")
+}
 
-    // 询问是否需要 API Key Helper
+/// 询问是否需要 API Key Helper
+fn prompt_helper() -> anyhow::Result<Option<String>> {
     let need_helper = Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
         .with_prompt("是否需要 API Key Helper?")
         .default(false)
         .interact()
         .context("读取输入失败")?;
 
-    let api_key_helper = if need_helper {
-        Some(
-            Input::with_theme(&dialoguer::theme::ColorfulTheme::default())
-                .with_prompt("请输入 API Key Helper 命令 (例如: echo 'sk-xxx')")
-                .interact()
-                .context("读取输入失败")?,
-        )
-    } else {
-        None
-    };
-
-    // 创建配置
-    let profile = Profile::new(
-        api_key_helper,
-        EnvConfig::new(Some(base_url), Some(api_key)),
-    );
-
-    config.insert_profile(name.to_string(), profile);
-    config.save().context("保存配置失败")?;
+    if !need_helper {
+        return Ok(None);
+    }
+    Input::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("请输入 API Key Helper 命令 (例如: echo 'sk-xxx')")
+        .interact()
+        .map(Some)
+        .context("读取输入失败")
+}
 
-    println!();
-    success(&format!("配置档案 '{}' 已创建", name));
-    info(&format!("提示: 使用 'ccc use {}' 切换到此配置", name));
+/// 询问是否需要其他环境变量，并逐个读取 `KEY=VALUE`
+fn prompt_extra_env(env: &mut EnvConfig) -> anyhow::Result<()> {
+    let need_extra_env = Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("是否需要设置其他环境变量 (例如 ANTHROPIC_MODEL)?")
+        .default(false)
+        .interact()
+        .context("读取输入失败")?;
 
-    Ok(())
+    if !need_extra_env {
+        return Ok(());
+    }
+    info("请逐个输入 KEY=VALUE，输入空行结束");
+    loop {
+        let line: String = Input::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt("环境变量")
+            .allow_empty(true)
+            .validate_with(|input: &String| {
+                if input.is_empty() || EnvConfig::parse_assignment(input).is_some() {
+                    Ok(())
+                } else {
+                    Err("格式应为 KEY=VALUE")
+                }
+            })
+            .interact()
+            .context("读取输入失败")?;
+        match EnvConfig::parse_assignment(&line) {
+            Some((key, value)) => env.set(key, value.to_string()),
+            None => return Ok(()),
+        }
+    }
 }
@@ -0,0 +1,332 @@
+//! Backup 命令
+//!
+//! 查看、恢复、比较和清理 settings.json 备份
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use colored::Colorize;
+
+use crate::{
+    config::{
+        BackupRetention, CccConfig, ConfigLock,
+        atomic::write_private,
+        backup::{
+            backup_settings, find_backup, list_backups, migrate_legacy_backups, parse_age,
+            prune_backups,
+        },
+        is_secret_env_key,
+        paths::get_claude_settings_path,
+    },
+    output::{emit, info, is_json, success, warn},
+};
+
+/// 列出所有备份
+///
+/// # Errors
+///
+/// 返回备份目录读取错误
+pub fn list() -> anyhow::Result<()> {
+    let backups = list_backups()?;
+
+    if is_json() {
+        let backups: Vec<_> = backups
+            .iter()
+            .map(|backup| {
+                serde_json::json!({
+                    "id": backup.id,
+                    "path": backup.path,
+                    "created": backup.created.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                })
+            })
+            .collect();
+        emit("backup list", serde_json::json!({ "backups": backups }));
+        return Ok(());
+    }
+
+    println!("settings.json 备份:");
+    if backups.is_empty() {
+        println!("  (无备份)");
+        return Ok(());
+    }
+
+    let latest = backups.last().map(|backup| backup.id.clone());
+    for backup in &backups {
+        let created = backup.created.format("%Y-%m-%d %H:%M:%S");
+        if latest.as_deref() == Some(backup.id.as_str()) {
+            println!("  {}  {} (最新)", backup.id.green(), created);
+        } else {
+            println!("  {}  {}", backup.id, created);
+        }
+    }
+    println!();
+    info(&format!("共 {} 个备份", backups.len()));
+
+    Ok(())
+}
+
+/// 从备份恢复 settings.json
+///
+/// 恢复前会先备份当前的 settings.json，此次备份不会按保留策略清理正在恢复的备份
+///
+/// # Errors
+///
+/// 返回备份不存在、备份内容无效、文件操作失败等错误
+pub fn restore(id: &str) -> anyhow::Result<()> {
+    let _lock = ConfigLock::acquire()?;
+    let backup = find_backup(id)?;
+
+    let content = std::fs::read_to_string(&backup.path).context("读取备份失败")?;
+    serde_json::from_str::<serde_json::Value>(&content)
+        .with_context(|| format!("备份 '{}' 不是有效的 JSON", backup.id))?;
+
+    let settings_path = get_claude_settings_path()?;
+    let backup_path = if settings_path.exists() {
+        let backup_path = backup_settings(Some(&backup.id)).context("备份失败")?;
+        info(&format!("已备份当前配置: {}", backup_path.display()));
+        Some(backup_path)
+    } else {
+        None
+    };
+
+    write_private(&settings_path, content.as_bytes()).context("写入 settings 失败")?;
+
+    emit(
+        "backup restore",
+        serde_json::json!({ "id": backup.id, "backup_path": backup_path }),
+    );
+    success(&format!("已从备份 '{}' 恢复 settings.json", backup.id));
+
+    Ok(())
+}
+
+/// 比较备份与当前 settings.json 的差异
+///
+/// # Errors
+///
+/// 返回备份不存在、文件读取或解析错误
+pub fn diff(id: &str) -> anyhow::Result<()> {
+    let backup = find_backup(id)?;
+
+    let backup_content = std::fs::read_to_string(&backup.path).context("读取备份失败")?;
+    let backup_value: serde_json::Value =
+        serde_json::from_str(&backup_content).context("解析备份失败")?;
+
+    let settings_path = get_claude_settings_path()?;
+    let current_value: serde_json::Value = if settings_path.exists() {
+        let content = std::fs::read_to_string(&settings_path).context("读取 settings 失败")?;
+        serde_json::from_str(&content).context("解析 settings 失败")?
+    } else {
+        serde_json::json!({})
+    };
+
+    let old = flatten(&backup_value);
+    let new = flatten(&current_value);
+
+    if is_json() {
+        let changes: Vec<_> = old
+            .keys()
+            .chain(new.keys().filter(|path| !old.contains_key(*path)))
+            .filter(|path| old.get(*path) != new.get(*path))
+            .map(|path| {
+                let mask = |value: &serde_json::Value| {
+                    if is_secret_path(path) {
+                        serde_json::Value::from(SECRET_PLACEHOLDER)
+                    } else {
+                        value.clone()
+                    }
+                };
+                serde_json::json!({
+                    "path": path,
+                    "old": old.get(path).map(mask),
+                    "new": new.get(path).map(mask),
+                })
+            })
+            .collect();
+        emit(
+            "backup diff",
+            serde_json::json!({ "id": backup.id, "changes": changes }),
+        );
+        return Ok(());
+    }
+
+    println!("--- 备份 {}", backup.id);
+    println!("+++ 当前 settings.json");
+
+    let print_removed = |path: &str, value: &serde_json::Value| {
+        println!(
+            "{}",
+            format!("- {}: {}", path, display_value(path, value)).red()
+        );
+    };
+    let print_added = |path: &str, value: &serde_json::Value| {
+        println!(
+            "{}",
+            format!("+ {}: {}", path, display_value(path, value)).green()
+        );
+    };
+
+    let mut changed = false;
+    for (path, old_value) in &old {
+        match new.get(path) {
+            Some(new_value) if new_value == old_value => {}
+            new_value => {
+                changed = true;
+                print_removed(path, old_value);
+                if let Some(new_value) = new_value {
+                    print_added(path, new_value);
+                }
+            }
+        }
+    }
+    for (path, new_value) in &new {
+        if !old.contains_key(path) {
+            changed = true;
+            print_added(path, new_value);
+        }
+    }
+
+    if !changed {
+        info("备份与当前 settings.json 相同");
+    }
+
+    Ok(())
+}
+
+/// 清理备份
+///
+/// `keep` 保留最新的 N 个备份，`older_than` 删除早于指定时长的备份（例如 `30d`）
+///
+/// # Errors
+///
+/// 返回参数缺失、时长格式错误、文件删除错误
+pub fn prune(keep: Option<usize>, older_than: Option<&str>) -> anyhow::Result<()> {
+    if keep.is_none() && older_than.is_none() {
+        anyhow::bail!("请至少指定 --keep 或 --older-than");
+    }
+    let older_than = older_than.map(parse_age).transpose()?;
+
+    let _lock = ConfigLock::acquire()?;
+    migrate_legacy_backups().context("移动旧版本备份失败")?;
+    let removed = prune_backups(keep, older_than)?;
+
+    if is_json() {
+        let ids: Vec<_> = removed.iter().map(|backup| &backup.id).collect();
+        emit("backup prune", serde_json::json!({ "removed": ids }));
+        return Ok(());
+    }
+
+    if removed.is_empty() {
+        warn("没有需要清理的备份");
+    } else {
+        for backup in &removed {
+            println!("  已删除: {}", backup.id);
+        }
+        success(&format!("已清理 {} 个备份", removed.len()));
+    }
+
+    Ok(())
+}
+
+/// 查看或设置备份保留策略
+///
+/// 两个参数均未指定时显示当前策略；`keep` 为 `0` 表示不限制数量，
+/// `max_age` 为 `none` 表示不限制时长
+///
+/// # Errors
+///
+/// 返回时长格式错误、配置文件读写错误
+pub fn retention(keep: Option<usize>, max_age: Option<&str>) -> anyhow::Result<()> {
+    if keep.is_none() && max_age.is_none() {
+        let config = CccConfig::load().context("加载配置失败")?;
+        print_retention(&config.backup_retention);
+        return Ok(());
+    }
+
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
+
+    if let Some(keep) = keep {
+        config.backup_retention.keep = keep;
+    }
+    if let Some(max_age) = max_age {
+        config.backup_retention.max_age = if max_age == "none" {
+            None
+        } else {
+            parse_age(max_age)?;
+            Some(max_age.to_string())
+        };
+    }
+    config.save().context("保存配置失败")?;
+
+    success("备份保留策略已更新");
+    print_retention(&config.backup_retention);
+
+    Ok(())
+}
+
+/// 显示备份保留策略
+fn print_retention(retention: &BackupRetention) {
+    if is_json() {
+        emit(
+            "backup retention",
+            serde_json::json!({ "keep": retention.keep, "max_age": retention.max_age }),
+        );
+        return;
+    }
+    if retention.keep == 0 {
+        println!("  保留数量: 不限制");
+    } else {
+        println!("  保留数量: {}", retention.keep);
+    }
+    println!(
+        "  保留时长: {}",
+        retention.max_age.as_deref().unwrap_or("不限制")
+    );
+}
+
+/// 将 JSON 展开为 `JSON Pointer -> 值` 的映射
+#[must_use]
+fn flatten(value: &serde_json::Value) -> BTreeMap<String, serde_json::Value> {
+    let mut result = BTreeMap::new();
+    flatten_into(value, String::new(), &mut result);
+    result
+}
+
+/// 递归展开 JSON 对象
+fn flatten_into(
+    value: &serde_json::Value,
+    prefix: String,
+    result: &mut BTreeMap<String, serde_json::Value>,
+) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                flatten_into(child, format!("{}/{}", prefix, escaped), result);
+            }
+        }
+        _ => {
+            result.insert(prefix, value.clone());
+        }
+    }
+}
+
+/// 敏感字段的占位文本
+const SECRET_PLACEHOLDER: &str = "***[已隐藏]";
+
+/// 判断 JSON Pointer 指向的字段是否为敏感字段
+#[must_use]
+fn is_secret_path(path: &str) -> bool {
+    is_secret_env_key(path.rsplit('/').next().unwrap_or_default())
+}
+
+/// 格式化差异中的值，敏感字段会被隐藏
+#[must_use]
+fn display_value(path: &str, value: &serde_json::Value) -> String {
+    if is_secret_path(path) {
+        SECRET_PLACEHOLDER.to_string()
+    } else {
+        value.to_string()
+    }
+}
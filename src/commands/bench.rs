@@ -0,0 +1,304 @@
+//! Bench 命令
+//!
+//! 并发测量各配置档案端点的延迟并排名
+
+use std::{
+    collections::BTreeMap,
+    process::ExitCode,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::Context;
+use colored::Colorize;
+
+use crate::{
+    commands::use_cmd::apply_profile,
+    config::{
+        CccConfig, ConfigLock, ManagedKeys, expand_profile, paths::SettingsScope, resolve_env,
+    },
+    error::CccError,
+    output::{emit, info, is_json, success, warn},
+    probe::{ProbeResult, percentile, probe},
+};
+
+/// 同时发送的探测请求数上限
+const MAX_CONCURRENT_PROBES: usize = 16;
+
+/// 配置档案名称及其解析后的环境变量（解析失败时为错误信息）
+type ResolvedProfile<'a> = (&'a str, Result<BTreeMap<String, String>, String>);
+
+/// 单个配置档案的测量结果
+#[derive(Debug, Clone, serde::Serialize)]
+struct BenchResult {
+    /// 配置名称
+    profile: String,
+    /// 请求次数
+    count: usize,
+    /// 失败次数
+    errors: usize,
+    /// 成功请求延迟的中位数（毫秒）
+    p50_ms: Option<u128>,
+    /// 成功请求延迟的 95 百分位数（毫秒）
+    p95_ms: Option<u128>,
+    /// 最后一次失败的错误信息
+    last_error: Option<String>,
+}
+
+impl BenchResult {
+    /// 所有请求均失败的结果
+    fn failed(profile: &str, count: usize, message: String) -> Self {
+        Self {
+            profile: profile.to_string(),
+            count,
+            errors: count,
+            p50_ms: None,
+            p95_ms: None,
+            last_error: Some(message),
+        }
+    }
+
+    /// 错误率（百分比）
+    #[must_use]
+    const fn error_rate(&self) -> usize {
+        self.errors * 100 / self.count
+    }
+}
+
+/// 对配置档案的端点各发送 `count` 次探测请求（最多同时发送 16 个），按错误率和 p50 延迟排名
+///
+/// `names` 为空时测量所有配置档案；`use_best` 为 `true` 时切换到排名第一的配置
+///
+/// # Errors
+///
+/// 返回配置档案不存在、参数无效、切换配置失败等错误
+pub fn execute(
+    names: &[String],
+    count: usize,
+    timeout: u64,
+    use_best: bool,
+) -> anyhow::Result<ExitCode> {
+    if count == 0 {
+        anyhow::bail!("--count 必须大于 0");
+    }
+    let config = CccConfig::load().context("加载配置失败")?;
+    let names: Vec<String> = if names.is_empty() {
+        let mut all: Vec<_> = config.profiles.keys().cloned().collect();
+        all.sort();
+        all
+    } else {
+        if let Some(missing) = names.iter().find(|name| !config.has_profile(name)) {
+            return Err(CccError::ProfileNotFound(missing.clone()).into());
+        }
+        names.to_vec()
+    };
+    if names.is_empty() {
+        anyhow::bail!("没有可测量的配置档案");
+    }
+
+    info(&format!(
+        "正在测量 {} 个配置档案 (每个 {} 次请求)...",
+        names.len(),
+        count
+    ));
+    // 在发送请求前依次解析所有配置档案，加密存储的口令最多只需输入一次
+    let resolved: Vec<_> = names
+        .iter()
+        .map(|name| {
+            let vars = config
+                .resolve_profile(name)
+                .and_then(|profile| expand_profile(name, &profile))
+                .and_then(|profile| resolve_env(&profile))
+                .map_err(|err| format!("{:#}", err));
+            (name.as_str(), vars)
+        })
+        .collect();
+    let mut results = run_probes(&resolved, count, Duration::from_secs(timeout));
+    rank(&mut results);
+
+    let best = results
+        .first()
+        .filter(|result| result.errors < result.count)
+        .map(|result| result.profile.clone());
+    let used = match (&best, use_best) {
+        (Some(best), true) => {
+            let _lock = ConfigLock::acquire()?;
+            let mut latest = CccConfig::load().context("加载配置失败")?;
+            apply_profile(
+                &mut latest,
+                best,
+                false,
+                SettingsScope::User,
+                &ManagedKeys::default(),
+            )?;
+            latest.save().context("保存配置失败")?;
+            Some(best.clone())
+        }
+        _ => None,
+    };
+
+    if is_json() {
+        let results: Vec<_> = results
+            .iter()
+            .map(|result| {
+                let mut value = serde_json::json!(result);
+                if let Some(object) = value.as_object_mut() {
+                    object.insert("error_rate".to_string(), result.error_rate().into());
+                }
+                value
+            })
+            .collect();
+        emit(
+            "bench",
+            serde_json::json!({ "results": results, "best": best, "used": used }),
+        );
+    } else {
+        print_results(&results);
+        match (&best, &used) {
+            (_, Some(used)) => success(&format!("已切换到最快的配置档案: {}", used)),
+            (Some(best), None) => info(&format!("最快的配置档案: {} (使用 --use 自动切换)", best)),
+            (None, _) => warn("所有配置档案均不可用"),
+        }
+    }
+
+    Ok(if best.is_some() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// 对已解析的配置档案各发送 `count` 次探测请求，最多同时发送 [`MAX_CONCURRENT_PROBES`] 个
+fn run_probes(
+    resolved: &[ResolvedProfile<'_>],
+    count: usize,
+    timeout: Duration,
+) -> Vec<BenchResult> {
+    // 轮流为各配置档案安排请求，避免排在后面的配置集中在同一时间段测量
+    let targets: Vec<_> = resolved
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (_, vars))| vars.as_ref().ok().map(|vars| (index, vars)))
+        .collect();
+    let jobs: Vec<_> = (0..count).flat_map(|_| targets.iter().copied()).collect();
+    let probes: Vec<Mutex<Vec<ProbeResult>>> =
+        resolved.iter().map(|_| Mutex::new(Vec::new())).collect();
+    let next = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.len().min(MAX_CONCURRENT_PROBES) {
+            scope.spawn(|| {
+                while let Some((index, vars)) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = probe(vars, timeout);
+                    if let Some(slot) = probes.get(*index) {
+                        slot.lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .push(result);
+                    }
+                }
+            });
+        }
+    });
+
+    resolved
+        .iter()
+        .zip(probes)
+        .map(|((name, vars), slot)| match vars {
+            Err(message) => BenchResult::failed(name, count, message.clone()),
+            Ok(_) => summarize(
+                name,
+                count,
+                &slot.into_inner().unwrap_or_else(PoisonError::into_inner),
+            ),
+        })
+        .collect()
+}
+
+/// 统计单个配置档案的探测结果，未完成的请求计为失败
+fn summarize(name: &str, count: usize, probes: &[ProbeResult]) -> BenchResult {
+    let mut latencies: Vec<_> = probes
+        .iter()
+        .filter(|result| result.is_ok())
+        .map(|result| result.latency_ms)
+        .collect();
+    latencies.sort_unstable();
+    BenchResult {
+        profile: name.to_string(),
+        count,
+        errors: count - latencies.len(),
+        p50_ms: percentile(&latencies, 50),
+        p95_ms: percentile(&latencies, 95),
+        last_error: probes
+            .iter()
+            .rev()
+            .find_map(ProbeResult::failure_reason)
+            .or_else(|| (probes.len() < count).then(|| "测量线程异常退出".to_string())),
+    }
+}
+
+/// 按错误率、p50 延迟、p95 延迟排名，完全失败的配置排在最后
+fn rank(results: &mut [BenchResult]) {
+    results.sort_by(|a, b| {
+        a.error_rate()
+            .cmp(&b.error_rate())
+            .then_with(|| {
+                a.p50_ms
+                    .unwrap_or(u128::MAX)
+                    .cmp(&b.p50_ms.unwrap_or(u128::MAX))
+            })
+            .then_with(|| {
+                a.p95_ms
+                    .unwrap_or(u128::MAX)
+                    .cmp(&b.p95_ms.unwrap_or(u128::MAX))
+            })
+            .then_with(|| a.profile.cmp(&b.profile))
+    });
+}
+
+/// 输出测量结果表格
+fn print_results(results: &[BenchResult]) {
+    let width = results
+        .iter()
+        .map(|result| result.profile.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    println!(
+        "  {:<width$}  {:>8}  {:>8}  {:>3}",
+        "配置",
+        "p50",
+        "p95",
+        "错误率",
+        width = width - 2
+    );
+    let format_ms =
+        |value: Option<u128>| value.map_or_else(|| "-".to_string(), |ms| format!("{}ms", ms));
+    for (index, result) in results.iter().enumerate() {
+        let line = format!(
+            "  {:<width$}  {:>8}  {:>8}  {:>5}%",
+            result.profile,
+            format_ms(result.p50_ms),
+            format_ms(result.p95_ms),
+            result.error_rate(),
+            width = width
+        );
+        if result.errors == result.count {
+            println!("{}", line.red());
+        } else if index == 0 {
+            println!("{}", line.green());
+        } else if result.errors > 0 {
+            println!("{}", line.yellow());
+        } else {
+            println!("{}", line);
+        }
+        if result.errors > 0
+            && let Some(error) = &result.last_error
+        {
+            println!("    {}", error);
+        }
+    }
+    println!();
+}
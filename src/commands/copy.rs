@@ -0,0 +1,56 @@
+//! Copy 命令
+//!
+//! 复制配置档案
+
+use anyhow::Context;
+
+use crate::{
+    config::{CccConfig, ConfigLock, validate_profile_name},
+    error::CccError,
+    output::{emit, info, success, warn},
+};
+
+/// 复制配置档案
+///
+/// `dst` 已存在时需指定 `force` 才会覆盖
+///
+/// # Errors
+///
+/// 返回配置名称无效、配置档案不存在、目标配置已存在等错误
+pub fn execute(src: &str, dst: &str, force: bool) -> anyhow::Result<()> {
+    if !validate_profile_name(dst) {
+        return Err(CccError::InvalidProfileName(dst.to_string()).into());
+    }
+    if src == dst {
+        anyhow::bail!("源配置与目标配置名称相同");
+    }
+
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
+
+    if !config.has_profile(src) {
+        return Err(CccError::ProfileNotFound(src.to_string()).into());
+    }
+    let overwritten = config.has_profile(dst);
+    if overwritten && !force {
+        return Err(CccError::ProfileExists(dst.to_string()).into());
+    }
+    let overwrote_current = overwritten && config.current.as_deref() == Some(dst);
+
+    let _ = config.copy_profile(src, dst);
+    config.save().context("保存配置失败")?;
+
+    emit(
+        "copy",
+        serde_json::json!({ "from": src, "to": dst, "overwritten": overwritten }),
+    );
+    if overwrote_current {
+        warn("已覆盖当前活跃配置，请使用 'ccc use <name>' 重新切换配置");
+    } else if overwritten {
+        warn(&format!("已覆盖配置档案 '{}'", dst));
+    }
+    success(&format!("配置档案 '{}' 已复制为 '{}'", src, dst));
+    info(&format!("提示: 使用 'ccc edit {}' 修改新配置", dst));
+
+    Ok(())
+}
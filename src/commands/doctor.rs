@@ -0,0 +1,550 @@
+//! Doctor 命令
+//!
+//! 诊断配置切换不生效等常见问题
+
+use std::{path::Path, process::ExitCode};
+
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::{
+    config::{
+        API_KEY_KEY, AUTH_TOKEN_KEY, BASE_URL_KEY, CccConfig, ClaudeEnv, ClaudeSettings,
+        backup::list_backups,
+        paths::{SettingsScope, get_ccc_config_path, get_vault_path},
+        permissions::find_insecure_files,
+        validate_profile_name,
+        vault::referenced_ids,
+    },
+    output::{emit, is_json},
+};
+
+/// 备份总大小超过该值时给出警告（10 MiB）
+const BACKUP_SIZE_WARN: u64 = 10 * 1024 * 1024;
+
+/// 会覆盖 settings.json 的 shell 环境变量
+const OVERRIDING_ENV_KEYS: [&str; 3] = [API_KEY_KEY, AUTH_TOKEN_KEY, BASE_URL_KEY];
+
+/// 检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    /// 通过
+    Pass,
+    /// 警告
+    Warn,
+    /// 失败
+    Fail,
+}
+
+/// 单项检查
+#[derive(Debug, Serialize)]
+struct Check {
+    /// 检查项 ID
+    id: &'static str,
+    /// 检查项名称
+    name: &'static str,
+    /// 检查结果
+    status: Status,
+    /// 结果说明
+    message: String,
+    /// 详细信息
+    details: Vec<String>,
+    /// 修复建议
+    fix: Option<String>,
+}
+
+impl Check {
+    /// 通过的检查
+    fn pass(id: &'static str, name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            id,
+            name,
+            status: Status::Pass,
+            message: message.into(),
+            details: Vec::new(),
+            fix: None,
+        }
+    }
+
+    /// 警告或失败的检查
+    fn problem(
+        id: &'static str,
+        name: &'static str,
+        status: Status,
+        message: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            status,
+            message: message.into(),
+            details: Vec::new(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    /// 附加详细信息
+    #[must_use]
+    fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// 运行所有诊断检查并输出结果
+///
+/// 每项检查的结果为 pass / warn / fail，并附带修复建议；存在失败项时以失败状态码退出
+///
+/// # Errors
+///
+/// 返回路径获取错误（单项检查失败不会返回错误）
+pub fn execute() -> anyhow::Result<ExitCode> {
+    let mut checks = Vec::new();
+
+    let config = match load_config() {
+        Ok(config) => {
+            checks.push(Check::pass("config_file", "配置文件", "存在且格式正确"));
+            Some(config)
+        }
+        Err(check) => {
+            checks.push(check);
+            None
+        }
+    };
+    if let Some(config) = &config {
+        checks.push(check_schema(config)?);
+    }
+
+    let user_settings_path = SettingsScope::User.settings_path()?;
+    let settings = match load_settings(&user_settings_path) {
+        Ok(settings) => {
+            checks.push(Check::pass(
+                "settings_file",
+                "settings.json",
+                "存在且格式正确",
+            ));
+            Some(settings)
+        }
+        Err(check) => {
+            checks.push(check);
+            None
+        }
+    };
+
+    checks.push(check_permissions());
+    checks.push(check_shell_env());
+    checks.push(check_project_settings(
+        &user_settings_path,
+        settings.as_ref(),
+    )?);
+    if let (Some(config), Some(settings)) = (&config, &settings) {
+        checks.push(check_api_key_helper(config, settings));
+    }
+    if let Some(config) = &config {
+        checks.push(check_current(config));
+    }
+    checks.push(check_backups());
+
+    let count = |status: Status| checks.iter().filter(|check| check.status == status).count();
+    let failed = count(Status::Fail);
+    if is_json() {
+        emit(
+            "doctor",
+            serde_json::json!({
+                "checks": checks,
+                "summary": {
+                    "pass": count(Status::Pass),
+                    "warn": count(Status::Warn),
+                    "fail": failed,
+                },
+            }),
+        );
+    } else {
+        print_checks(&checks);
+        println!();
+        println!(
+            "{} 项通过，{} 项警告，{} 项失败",
+            count(Status::Pass),
+            count(Status::Warn),
+            failed
+        );
+    }
+
+    Ok(if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// 输出检查结果
+fn print_checks(checks: &[Check]) {
+    for check in checks {
+        let mark = match check.status {
+            Status::Pass => "✓".green(),
+            Status::Warn => "!".yellow(),
+            Status::Fail => "✗".red(),
+        };
+        println!("{} {}: {}", mark, check.name, check.message);
+        for detail in &check.details {
+            println!("    {}", detail);
+        }
+        if let Some(fix) = &check.fix {
+            println!("    建议: {}", fix);
+        }
+    }
+}
+
+/// 读取 cccrs 配置文件（不会自动创建）
+fn load_config() -> Result<CccConfig, Check> {
+    const ID: &str = "config_file";
+    const NAME: &str = "配置文件";
+
+    let path = get_ccc_config_path().map_err(|err| {
+        Check::problem(
+            ID,
+            NAME,
+            Status::Fail,
+            format!("无法确定路径: {}", err),
+            "设置 HOME 环境变量或使用 --config-dir 指定配置目录",
+        )
+    })?;
+    if !path.exists() {
+        return Err(Check::problem(
+            ID,
+            NAME,
+            Status::Warn,
+            format!("{} 不存在", path.display()),
+            "运行 cccrs init 或 cccrs add <name> 创建配置",
+        ));
+    }
+    std::fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| Ok(serde_json::from_str(&content)?))
+        .map_err(|err| {
+            Check::problem(
+                ID,
+                NAME,
+                Status::Fail,
+                format!("{} 无法解析: {}", path.display(), err),
+                "修正 JSON 格式后重试",
+            )
+        })
+}
+
+/// 读取用户级 settings.json
+fn load_settings(path: &Path) -> Result<ClaudeSettings, Check> {
+    const ID: &str = "settings_file";
+    const NAME: &str = "settings.json";
+
+    if !path.exists() {
+        return Err(Check::problem(
+            ID,
+            NAME,
+            Status::Warn,
+            format!("{} 不存在", path.display()),
+            "运行 cccrs use <name> 写入配置",
+        ));
+    }
+    ClaudeSettings::load_from(path).map_err(|err| {
+        Check::problem(
+            ID,
+            NAME,
+            Status::Fail,
+            format!("{} 无法解析: {:#}", path.display(), err),
+            "修正 JSON 格式，或运行 cccrs backup restore latest 从备份恢复",
+        )
+    })
+}
+
+/// 检查配置档案内容：名称、环境变量名、继承关系、备用配置、必要字段和加密存储引用
+fn check_schema(config: &CccConfig) -> anyhow::Result<Check> {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    let mut names: Vec<_> = config.profiles.keys().collect();
+    names.sort();
+    for name in names {
+        let Some(profile) = config.get_profile(name) else {
+            continue;
+        };
+        if !validate_profile_name(name) {
+            errors.push(format!("'{}': 无效的配置名称", name));
+        }
+        if let Some(key) = profile.env.invalid_key() {
+            errors.push(format!("'{}': 无效的环境变量名 '{}'", name, key));
+        }
+        match config.resolve_profile(name) {
+            Err(err) => errors.push(format!("'{}': {:#}", name, err)),
+            Ok(resolved) => {
+                if resolved.env.anthropic_base_url.is_none() {
+                    warnings.push(format!("'{}': 未设置 {}", name, BASE_URL_KEY));
+                }
+                if resolved.env.anthropic_api_key.is_none()
+                    && resolved.env.get(API_KEY_KEY).is_none()
+                    && resolved.api_key_helper.is_none()
+                {
+                    warnings.push(format!("'{}': 未设置 API Key 或 apiKeyHelper", name));
+                }
+            }
+        }
+        if let Err(err) = config.fallback_candidates(name) {
+            errors.push(format!("'{}': {:#}", name, err));
+        }
+    }
+
+    let references = config
+        .profiles
+        .values()
+        .flat_map(|profile| profile.env.vars().into_values().flat_map(referenced_ids))
+        .count();
+    if references > 0 && !get_vault_path()?.exists() {
+        errors.push(format!(
+            "{} 个字段引用了加密存储，但加密存储不存在",
+            references
+        ));
+    }
+
+    let count = errors.len() + warnings.len();
+    let (status, fix) = if !errors.is_empty() {
+        (Status::Fail, "使用 cccrs edit <name> 修正上述配置档案")
+    } else if !warnings.is_empty() {
+        (Status::Warn, "使用 cccrs edit <name> 补充缺少的字段")
+    } else {
+        return Ok(Check::pass(
+            "config_schema",
+            "配置档案",
+            format!("{} 个配置档案均有效", config.profiles.len()),
+        ));
+    };
+    errors.extend(warnings);
+    Ok(Check::problem(
+        "config_schema",
+        "配置档案",
+        status,
+        format!("发现 {} 个问题", count),
+        fix,
+    )
+    .with_details(errors))
+}
+
+/// 检查敏感文件权限
+fn check_permissions() -> Check {
+    const ID: &str = "permissions";
+    const NAME: &str = "文件权限";
+
+    match find_insecure_files() {
+        Ok(insecure) if insecure.is_empty() => {
+            Check::pass(ID, NAME, "包含 API Key 的文件仅当前用户可读写")
+        }
+        Ok(insecure) => Check::problem(
+            ID,
+            NAME,
+            Status::Warn,
+            format!("{} 个包含 API Key 的文件可被其他用户访问", insecure.len()),
+            "运行 cccrs permissions --fix",
+        )
+        .with_details(
+            insecure
+                .iter()
+                .map(|file| format!("{:03o} {}", file.mode, file.path.display()))
+                .collect(),
+        ),
+        Err(err) => Check::problem(
+            ID,
+            NAME,
+            Status::Warn,
+            format!("无法检查: {:#}", err),
+            "确认 Claude 配置目录可以访问",
+        ),
+    }
+}
+
+/// 检查 shell 中是否设置了会覆盖 settings.json 的环境变量
+fn check_shell_env() -> Check {
+    const ID: &str = "shell_env";
+    const NAME: &str = "shell 环境变量";
+
+    let set: Vec<_> = OVERRIDING_ENV_KEYS
+        .into_iter()
+        .filter(|key| std::env::var_os(key).is_some_and(|value| !value.is_empty()))
+        .collect();
+    if set.is_empty() {
+        return Check::pass(ID, NAME, "未设置会覆盖 settings.json 的环境变量");
+    }
+    Check::problem(
+        ID,
+        NAME,
+        Status::Warn,
+        format!(
+            "{} 已在 shell 中设置，可能覆盖 settings.json 中的配置",
+            set.join(", ")
+        ),
+        format!(
+            "执行 unset {} (或 eval \"$(cccrs env --unset)\")，并检查 shell 启动脚本",
+            set.join(" ")
+        ),
+    )
+}
+
+/// 检查项目级 settings 是否覆盖了用户级 settings 中的字段
+fn check_project_settings(
+    user_settings_path: &Path,
+    user_settings: Option<&ClaudeSettings>,
+) -> anyhow::Result<Check> {
+    const ID: &str = "project_settings";
+    const NAME: &str = "项目级 settings";
+
+    let user_vars = user_settings
+        .and_then(|settings| settings.env.as_ref())
+        .map(ClaudeEnv::vars)
+        .unwrap_or_default();
+    let user_helper = user_settings.and_then(|settings| settings.api_key_helper.as_deref());
+
+    let mut details = Vec::new();
+    for scope in [SettingsScope::Project, SettingsScope::Local] {
+        // 不在项目目录中时没有需要检查的项目级 settings
+        let Ok(path) = scope.settings_path() else {
+            continue;
+        };
+        if path == user_settings_path || !path.is_file() {
+            continue;
+        }
+        let Ok(settings) = ClaudeSettings::load_from(&path) else {
+            details.push(format!("{}: 无法解析", path.display()));
+            continue;
+        };
+        let mut shadowed: Vec<String> = settings
+            .env
+            .as_ref()
+            .map(ClaudeEnv::vars)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(key, value)| {
+                OVERRIDING_ENV_KEYS.contains(&key.as_str())
+                    || user_vars.get(key).is_some_and(|user| user != value)
+            })
+            .map(|(key, _)| key)
+            .collect();
+        if settings
+            .api_key_helper
+            .as_deref()
+            .is_some_and(|helper| Some(helper) != user_helper)
+        {
+            shadowed.push("apiKeyHelper".to_string());
+        }
+        if !shadowed.is_empty() {
+            details.push(format!("{}: {}", path.display(), shadowed.join(", ")));
+        }
+    }
+
+    if details.is_empty() {
+        return Ok(Check::pass(ID, NAME, "当前项目没有覆盖用户级配置"));
+    }
+    Ok(Check::problem(
+        ID,
+        NAME,
+        Status::Warn,
+        "当前项目的 settings 覆盖了用户级配置中的字段",
+        "使用 cccrs use <name> --scope project|local 切换项目配置，或从上述文件中删除这些字段",
+    )
+    .with_details(details))
+}
+
+/// 检查 settings.json 中的 `apiKeyHelper` 是否属于当前配置
+fn check_api_key_helper(config: &CccConfig, settings: &ClaudeSettings) -> Check {
+    const ID: &str = "api_key_helper";
+    const NAME: &str = "apiKeyHelper";
+
+    let Some(helper) = settings.api_key_helper.as_deref() else {
+        return Check::pass(ID, NAME, "settings.json 未设置 apiKeyHelper");
+    };
+    let current = config.current.as_deref();
+    let expected = current
+        .and_then(|name| config.resolve_profile(name).ok())
+        .and_then(|profile| profile.api_key_helper);
+    if expected.as_deref() == Some(helper) {
+        return Check::pass(ID, NAME, "与当前配置一致");
+    }
+    // 只有 cccrs 写入的字段才能通过 --clean 移除
+    let fix = match current.filter(|name| config.has_profile(name)) {
+        Some(name) if config.managed.api_key_helper => {
+            format!("运行 cccrs use {} --clean 移除之前配置留下的字段", name)
+        }
+        _ => "确认是否需要该 apiKeyHelper，不需要时从 settings.json 中删除".to_string(),
+    };
+    let mut owners: Vec<_> = config
+        .profiles
+        .iter()
+        .filter(|(_, profile)| profile.api_key_helper.as_deref() == Some(helper))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    owners.sort_unstable();
+    let message = if owners.is_empty() {
+        "settings.json 中的 apiKeyHelper 不属于任何配置档案".to_string()
+    } else {
+        format!(
+            "settings.json 中的 apiKeyHelper 来自 {}，而非当前配置",
+            owners.join(", ")
+        )
+    };
+    Check::problem(ID, NAME, Status::Warn, message, fix)
+}
+
+/// 检查当前活跃配置记录是否指向存在的配置档案
+fn check_current(config: &CccConfig) -> Check {
+    const ID: &str = "current";
+    const NAME: &str = "当前配置";
+
+    match config.current.as_deref() {
+        None => Check::pass(ID, NAME, "未记录当前配置"),
+        Some(name) if config.has_profile(name) => {
+            Check::pass(ID, NAME, format!("当前配置为 '{}'", name))
+        }
+        Some(name) => Check::problem(
+            ID,
+            NAME,
+            Status::Fail,
+            format!("记录的当前配置 '{}' 已不存在", name),
+            "运行 cccrs use <name> 切换到存在的配置",
+        ),
+    }
+}
+
+/// 检查备份占用的空间
+fn check_backups() -> Check {
+    const ID: &str = "backups";
+    const NAME: &str = "备份";
+
+    let backups = match list_backups() {
+        Ok(backups) => backups,
+        Err(err) => {
+            return Check::problem(
+                ID,
+                NAME,
+                Status::Warn,
+                format!("无法读取备份目录: {:#}", err),
+                "确认备份目录可以访问",
+            );
+        }
+    };
+    let size: u64 = backups
+        .iter()
+        .filter_map(|backup| std::fs::metadata(&backup.path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    let message = format!("{} 个备份，共 {} KiB", backups.len(), size.div_ceil(1024));
+    if size > BACKUP_SIZE_WARN {
+        Check::problem(
+            ID,
+            NAME,
+            Status::Warn,
+            message,
+            "运行 cccrs backup prune --keep 20，或使用 cccrs backup retention 设置自动清理",
+        )
+    } else {
+        Check::pass(ID, NAME, message)
+    }
+}
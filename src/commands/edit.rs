@@ -0,0 +1,288 @@
+//! Edit 命令
+//!
+//! 修改已有的配置档案
+
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use dialoguer::Confirm;
+
+use crate::{
+    commands::use_cmd::{apply_profile, describe_keys},
+    config::{
+        CccConfig, ConfigLock, EnvConfig, ManagedKeys, Profile,
+        atomic::write_private,
+        paths::{SettingsScope, get_claude_dir},
+        resolve::shell_command,
+    },
+    error::CccError,
+    output::{emit, error, info, success},
+};
+
+/// `--set` / `--unset` 中表示 API Key Helper 的字段名（与 settings.json 一致）
+pub const HELPER_FIELD: &str = "apiKeyHelper";
+
+/// `--set` / `--unset` 中表示继承的父配置档案的字段名
+pub const EXTENDS_FIELD: &str = "extends";
+
+/// `--set` / `--unset` 中表示备用配置档案列表的字段名（`--set` 时以逗号分隔）
+pub const FALLBACK_FIELD: &str = "fallback";
+
+/// 修改配置档案
+///
+/// 指定 `set` 或 `unset` 时直接修改对应字段（`apiKeyHelper` 表示 API Key Helper，
+/// `extends` 表示继承的父配置，`fallback` 表示备用配置列表，其余为环境变量）；否则在 `$VISUAL` / `$EDITOR` 中以 JSON 形式编辑配置档案，
+/// 保存后校验。当前活跃配置（或其继承的配置）被修改时，会自动重新写入 settings.json
+///
+/// # Errors
+///
+/// 返回配置档案不存在、字段格式错误、编辑器运行失败、JSON 校验失败、继承存在循环、备用配置不存在等错误
+pub fn execute(name: &str, set: &[String], unset: &[String]) -> anyhow::Result<()> {
+    let original = CccConfig::load()
+        .context("加载配置失败")?
+        .get_profile(name)
+        .cloned()
+        .ok_or_else(|| CccError::ProfileNotFound(name.to_string()))?;
+
+    let edited = if set.is_empty() && unset.is_empty() {
+        edit_in_editor(name, &original)?
+    } else {
+        apply_changes(&original, set, unset)?
+    };
+
+    // 编辑期间配置可能已被其他进程修改，加锁后重新加载
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
+    if config.get_profile(name) != Some(&original) {
+        anyhow::bail!("编辑期间配置档案 '{}' 已被其他进程修改，请重试", name);
+    }
+
+    if edited == original {
+        emit(
+            "edit",
+            serde_json::json!({ "profile": name, "changed": false, "reapplied": false }),
+        );
+        info("配置档案未修改");
+        return Ok(());
+    }
+
+    // 修改前当前活跃配置解析后的字段，用于找出被删除的字段
+    let current = config.current.clone().filter(|current| {
+        config
+            .extends_chain(current)
+            .is_ok_and(|chain| chain.contains(&name))
+    });
+    let before = current
+        .as_deref()
+        .map(|current| config.resolve_profile(current))
+        .transpose()?;
+
+    config.insert_profile(name.to_string(), edited);
+    // 校验继承关系：父配置存在且没有循环
+    config.resolve_profile(name)?;
+    // 校验备用配置存在
+    config.fallback_candidates(name)?;
+
+    // 当前活跃配置被修改时重新写入 settings.json，并移除被删除的字段
+    let reapplied = match (current, before) {
+        (Some(current), Some(before)) => {
+            let after = config.resolve_profile(&current)?;
+            let removed =
+                ManagedKeys::from_profile(&before).difference(&ManagedKeys::from_profile(&after));
+            Some(apply_profile(
+                &mut config,
+                &current,
+                false,
+                SettingsScope::User,
+                &removed,
+            )?)
+        }
+        _ => None,
+    };
+    config.save().context("保存配置失败")?;
+
+    emit(
+        "edit",
+        serde_json::json!({
+            "profile": name,
+            "changed": true,
+            "reapplied": reapplied.is_some(),
+            "backup_path": reapplied.as_ref().and_then(|applied| applied.backup_path.clone()),
+            "removed": reapplied.as_ref().map(|applied| describe_keys(&applied.removed)),
+        }),
+    );
+    success(&format!("配置档案 '{}' 已更新", name));
+    if reapplied.is_some() {
+        info("已将修改应用到 settings.json");
+    }
+
+    Ok(())
+}
+
+/// 按 `KEY=VALUE` / `KEY` 列表修改配置档案
+fn apply_changes(original: &Profile, set: &[String], unset: &[String]) -> anyhow::Result<Profile> {
+    let mut profile = original.clone();
+
+    for key in unset {
+        if key == HELPER_FIELD {
+            profile.api_key_helper = None;
+        } else if key == EXTENDS_FIELD {
+            profile.extends = None;
+        } else if key == FALLBACK_FIELD {
+            profile.fallback.clear();
+        } else if !EnvConfig::is_valid_key(key) {
+            anyhow::bail!("无效的环境变量名 '{}'", key);
+        } else if profile.env.remove(key).is_none() {
+            anyhow::bail!("配置档案中没有字段 '{}'", key);
+        }
+    }
+
+    for assignment in set {
+        match assignment.split_once('=') {
+            Some((HELPER_FIELD, command)) => profile.api_key_helper = Some(command.to_string()),
+            Some((EXTENDS_FIELD, parent)) => profile.extends = Some(parent.to_string()),
+            Some((FALLBACK_FIELD, list)) => {
+                profile.fallback = list
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|fallback| !fallback.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            _ => {
+                let (key, value) = EnvConfig::parse_assignment(assignment).ok_or_else(|| {
+                    anyhow::anyhow!("无效的赋值 '{}': 格式应为 KEY=VALUE", assignment)
+                })?;
+                profile.env.set(key, value.to_string());
+            }
+        }
+    }
+
+    validate_profile(&profile)?;
+    Ok(profile)
+}
+
+/// 在编辑器中以 JSON 形式编辑配置档案
+///
+/// 校验失败时可重新编辑（stdin 为终端时），否则返回错误
+fn edit_in_editor(name: &str, original: &Profile) -> anyhow::Result<Profile> {
+    let path = edit_file_path(name)?;
+    let mut content = serde_json::to_string_pretty(original).context("序列化配置档案失败")?;
+    content.push('\n');
+
+    let result = edit_until_valid(&path, content);
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// 反复编辑直到内容通过校验或用户放弃
+fn edit_until_valid(path: &Path, mut content: String) -> anyhow::Result<Profile> {
+    loop {
+        // 配置档案中包含 API Key，临时文件只允许当前用户读写
+        write_private(path, content.as_bytes()).context("创建临时文件失败")?;
+        run_editor(path)?;
+        content = std::fs::read_to_string(path).context("读取编辑结果失败")?;
+
+        let err = match parse_profile(&content) {
+            Ok(profile) => return Ok(profile),
+            Err(err) => err,
+        };
+        if !std::io::stdin().is_terminal() {
+            return Err(err);
+        }
+        error(&format!("{:#}", err));
+        let retry = Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt("是否重新编辑?")
+            .default(true)
+            .interact()
+            .context("读取输入失败")?;
+        if !retry {
+            return Err(err);
+        }
+    }
+}
+
+/// 解析并校验编辑后的配置档案
+fn parse_profile(content: &str) -> anyhow::Result<Profile> {
+    let profile: Profile = serde_json::from_str(content).context("配置档案不是有效的 JSON")?;
+    validate_profile(&profile)?;
+    Ok(profile)
+}
+
+/// 校验配置档案内容
+fn validate_profile(profile: &Profile) -> anyhow::Result<()> {
+    if let Some(key) = profile.env.invalid_key() {
+        anyhow::bail!("无效的环境变量名 '{}'", key);
+    }
+    if profile
+        .api_key_helper
+        .as_deref()
+        .is_some_and(|helper| helper.trim().is_empty())
+    {
+        anyhow::bail!("API Key Helper 不能为空");
+    }
+    if profile
+        .fallback
+        .iter()
+        .any(|fallback| fallback.trim().is_empty())
+    {
+        anyhow::bail!("备用配置名称不能为空");
+    }
+    if profile.env.is_empty() && profile.api_key_helper.is_none() && profile.extends.is_none() {
+        anyhow::bail!("配置档案不能为空");
+    }
+    Ok(())
+}
+
+/// 获取编辑用临时文件的路径（位于 Claude 配置目录）
+fn edit_file_path(name: &str) -> anyhow::Result<PathBuf> {
+    let dir = get_claude_dir()?;
+    std::fs::create_dir_all(&dir).context("创建 Claude 配置目录失败")?;
+    Ok(dir.join(format!(".cccrs-edit-{}.{}.json", name, std::process::id())))
+}
+
+/// 构造运行编辑器的命令
+///
+/// `editor` 可以包含参数（例如 `code --wait`），由 shell 解析；Unix 平台下文件路径作为位置参数传入，
+/// 避免路径中的引号、`$` 等字符被 shell 解析
+fn editor_command(editor: &str, path: &Path) -> Command {
+    if cfg!(windows) {
+        return shell_command(&format!("{} \"{}\"", editor, path.display()));
+    }
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path);
+    command
+}
+
+/// 运行编辑器
+///
+/// 依次使用 `VISUAL`、`EDITOR` 环境变量，均未设置时使用平台默认编辑器
+fn run_editor(path: &Path) -> anyhow::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        });
+
+    let status = editor_command(&editor, path)
+        .status()
+        .with_context(|| format!("启动编辑器 '{}' 失败", editor))?;
+    if !status.success() {
+        anyhow::bail!("编辑器 '{}' 异常退出 ({})", editor, status);
+    }
+    Ok(())
+}
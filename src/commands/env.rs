@@ -0,0 +1,53 @@
+//! Env 命令
+//!
+//! 输出设置或清除配置档案环境变量的 shell 语句，供 `eval` 使用
+
+use anyhow::Context;
+
+use crate::{
+    config::{CccConfig, expand_profile, resolve_env},
+    output::{emit, is_json},
+    shell::Shell,
+};
+
+/// 输出配置档案的环境变量语句
+///
+/// 例如 `eval "$(cccrs env kimi)"` 可在当前 shell 中使用 kimi 配置，不修改 settings.json。
+/// `unset` 为 `true` 时输出清除语句；未指定配置名称时清除所有配置档案涉及的环境变量
+///
+/// # Errors
+///
+/// 返回配置档案不存在、未指定配置名称、占位符解析失败、`apiKeyHelper` 执行失败等错误
+pub fn execute(name: Option<&str>, shell: Option<Shell>, unset: bool) -> anyhow::Result<()> {
+    let config = CccConfig::load().context("加载配置失败")?;
+    let shell = shell.unwrap_or_else(Shell::detect);
+
+    if unset {
+        let keys = match name {
+            Some(name) => config.resolve_profile(name)?.env_keys(),
+            None => config.all_env_keys(),
+        };
+        if is_json() {
+            emit("env", serde_json::json!({ "unset": keys }));
+            return Ok(());
+        }
+        for key in keys {
+            println!("{}", shell.unset(&key)?);
+        }
+        return Ok(());
+    }
+
+    let name = name.ok_or_else(|| anyhow::anyhow!("请指定配置名称"))?;
+    let profile = expand_profile(name, &config.resolve_profile(name)?)?;
+    let vars =
+        resolve_env(&profile).with_context(|| format!("解析配置档案 '{}' 的环境变量失败", name))?;
+    if is_json() {
+        emit("env", serde_json::json!({ "profile": name, "vars": vars }));
+        return Ok(());
+    }
+    for (key, value) in &vars {
+        println!("{}", shell.export(key, value)?);
+    }
+
+    Ok(())
+}
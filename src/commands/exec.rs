@@ -0,0 +1,79 @@
+//! Exec 命令
+//!
+//! 使用指定配置档案的环境变量运行命令，不修改 settings.json
+
+use std::process::{Command, ExitCode};
+
+use anyhow::Context;
+
+use crate::config::{
+    API_KEY_KEY, AUTH_TOKEN_KEY, BASE_URL_KEY, CccConfig, expand_profile,
+    paths::{CLAUDE_CONFIG_DIR_ENV, config_dir_override},
+    resolve_env,
+};
+
+/// 无论配置档案是否设置都从子进程环境中移除的变量，避免继承的凭据覆盖配置档案
+const CREDENTIAL_ENV_KEYS: [&str; 3] = [API_KEY_KEY, AUTH_TOKEN_KEY, BASE_URL_KEY];
+
+/// 使用配置档案运行命令
+///
+/// 子进程会继承当前环境，并额外设置配置档案中的环境变量（以及 `apiKeyHelper` 的输出）。
+/// 配置档案未设置的凭据变量和其他配置档案的环境变量会被移除，避免继承的值覆盖配置档案；
+/// 指定了 `--config-dir` 时通过 `CLAUDE_CONFIG_DIR` 传给子进程。
+/// Unix 平台下直接替换当前进程，信号和退出码由子进程自然接管；
+/// 其他平台下等待子进程结束并转发退出码
+///
+/// # Errors
+///
+/// 返回配置档案不存在、占位符解析失败、`apiKeyHelper` 执行失败、命令启动失败等错误
+pub fn execute(name: &str, command: &[String]) -> anyhow::Result<ExitCode> {
+    let config = CccConfig::load().context("加载配置失败")?;
+
+    let profile = expand_profile(name, &config.resolve_profile(name)?)?;
+
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("缺少要运行的命令"))?;
+
+    let vars =
+        resolve_env(&profile).with_context(|| format!("解析配置档案 '{}' 的环境变量失败", name))?;
+
+    let mut child = Command::new(program);
+    child.args(args);
+    let inherited = config
+        .all_env_keys()
+        .into_iter()
+        .chain(CREDENTIAL_ENV_KEYS.map(str::to_string));
+    for key in inherited.filter(|key| !vars.contains_key(key)) {
+        child.env_remove(key);
+    }
+    child.envs(&vars);
+    if let Some(dir) = config_dir_override() {
+        child.env(CLAUDE_CONFIG_DIR_ENV, dir);
+    }
+
+    run(child, program)
+}
+
+/// 用子命令替换当前进程 (Unix only)
+#[cfg(unix)]
+fn run(mut child: Command, program: &str) -> anyhow::Result<ExitCode> {
+    use std::os::unix::process::CommandExt;
+
+    // exec 只在失败时返回
+    let error = child.exec();
+    Err(error).with_context(|| format!("运行命令失败: {}", program))
+}
+
+/// 运行子命令并转发退出码
+#[cfg(not(unix))]
+fn run(mut child: Command, program: &str) -> anyhow::Result<ExitCode> {
+    let status = child
+        .status()
+        .with_context(|| format!("运行命令失败: {}", program))?;
+    let code = status
+        .code()
+        .and_then(|code| u8::try_from(code).ok())
+        .unwrap_or(1);
+    Ok(ExitCode::from(code))
+}
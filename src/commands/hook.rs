@@ -0,0 +1,258 @@
+//! Hook 命令
+//!
+//! 生成 shell hook，进入包含 `.cccrs` / `.cccrs.toml` 的目录时自动设置对应配置档案的环境变量，
+//! 离开时恢复进入前的值
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Context;
+
+use crate::{
+    config::{
+        CccConfig, EnvConfig,
+        dir_profile::find_dir_profile,
+        expand_profile,
+        paths::{CCCRS_CONFIG_ENV, config_dir_override},
+        resolve_env,
+    },
+    output::{emit, is_json},
+    shell::Shell,
+};
+
+/// 记录当前生效的目录配置文件路径的环境变量
+pub const STATE_FILE_ENV: &str = "CCCRS_DIR_FILE";
+
+/// 记录当前生效的配置名称的环境变量
+pub const STATE_PROFILE_ENV: &str = "CCCRS_DIR_PROFILE";
+
+/// 记录 hook 设置的环境变量名（以逗号分隔）的环境变量
+pub const STATE_KEYS_ENV: &str = "CCCRS_DIR_KEYS";
+
+/// 记录被覆盖的环境变量原值的环境变量名前缀（后接变量名），进入前未设置的变量不记录
+pub const STATE_PREV_PREFIX: &str = "CCCRS_DIR_PREV_";
+
+/// 不支持 hook 的 shell 的错误信息
+const UNSUPPORTED_SHELL: &str = "hook 只支持 bash、zsh 和 fish";
+
+/// 输出 shell hook 脚本，`apply` 为 `true` 时输出当前目录需要执行的语句（由 hook 在每次显示提示符时调用）
+///
+/// 例如在 `~/.bashrc` 中加入 `eval "$(cccrs hook bash)"`。生成脚本时指定的 `--config-dir` 和
+/// `CCCRS_CONFIG` 会写入脚本，hook 之后使用相同的配置
+///
+/// # Errors
+///
+/// 返回不支持的 shell、获取当前目录失败等错误
+pub fn execute(shell: Shell, apply: bool) -> anyhow::Result<()> {
+    if shell == Shell::Powershell {
+        anyhow::bail!(UNSUPPORTED_SHELL);
+    }
+    if apply {
+        return apply_dir_profile(shell);
+    }
+
+    let script = hook_script(shell, &hook_command(shell))?;
+    if is_json() {
+        emit(
+            "hook",
+            serde_json::json!({ "shell": shell.to_string(), "script": script }),
+        );
+    } else {
+        print!("{}", script);
+    }
+    Ok(())
+}
+
+/// 生成 hook 中调用 cccrs 的命令（已按 shell 规则加引号），包括当前使用的配置文件和配置目录
+fn hook_command(shell: Shell) -> String {
+    let program = std::env::current_exe().ok().map_or_else(
+        || "cccrs".to_string(),
+        |path| path.to_string_lossy().into_owned(),
+    );
+    let mut parts = Vec::new();
+    if let Some(config) = std::env::var_os(CCCRS_CONFIG_ENV).filter(|value| !value.is_empty()) {
+        let config = std::path::absolute(&config).unwrap_or_else(|_| config.into());
+        parts.push("env".to_string());
+        parts.push(format!(
+            "{}={}",
+            CCCRS_CONFIG_ENV,
+            shell.quote(&config.to_string_lossy())
+        ));
+    }
+    parts.push(shell.quote(&program));
+    if let Some(dir) = config_dir_override() {
+        parts.push("--config-dir".to_string());
+        parts.push(shell.quote(&dir.to_string_lossy()));
+    }
+    parts.join(" ")
+}
+
+/// 生成 hook 脚本，`program` 为已按 shell 规则加引号的 cccrs 命令
+///
+/// # Errors
+///
+/// `PowerShell` 不支持 hook，返回错误
+fn hook_script(shell: Shell, program: &str) -> anyhow::Result<String> {
+    Ok(match shell {
+        Shell::Bash => format!(
+            r#"_cccrs_hook() {{
+  local previous_exit_status=$?
+  eval "$({program} hook bash --apply)"
+  return $previous_exit_status
+}}
+if [[ ";${{PROMPT_COMMAND[*]:-}};" != *";_cccrs_hook;"* ]]; then
+  if [[ "$(declare -p PROMPT_COMMAND 2>&1)" == "declare -a"* ]]; then
+    PROMPT_COMMAND=(_cccrs_hook "${{PROMPT_COMMAND[@]}}")
+  else
+    PROMPT_COMMAND="_cccrs_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+  fi
+fi
+"#
+        ),
+        Shell::Zsh => format!(
+            r#"_cccrs_hook() {{
+  eval "$({program} hook zsh --apply)"
+}}
+typeset -ag precmd_functions chpwd_functions
+if (( ! ${{precmd_functions[(I)_cccrs_hook]}} )); then
+  precmd_functions=(_cccrs_hook $precmd_functions)
+fi
+if (( ! ${{chpwd_functions[(I)_cccrs_hook]}} )); then
+  chpwd_functions=(_cccrs_hook $chpwd_functions)
+fi
+"#
+        ),
+        Shell::Fish => format!(
+            r"function __cccrs_hook --on-event fish_prompt --on-variable PWD
+    {program} hook fish --apply | source
+end
+"
+        ),
+        Shell::Powershell => anyhow::bail!(UNSUPPORTED_SHELL),
+    })
+}
+
+/// 对比当前目录的目录配置和上次设置的状态，输出需要执行的清除和设置语句
+///
+/// 目录配置未变化时不输出任何内容；切换或离开时将上次设置的环境变量恢复为进入前的值（之前未设置的清除）。
+/// 提示信息输出到 stderr，解析失败时也会记录状态，避免每次显示提示符都重复报错
+fn apply_dir_profile(shell: Shell) -> anyhow::Result<()> {
+    let cwd = std::env::current_dir().context("获取当前目录失败")?;
+    let found = match find_dir_profile(&cwd) {
+        Ok(found) => found,
+        Err(err) => {
+            eprintln!("cccrs: {:#}", err);
+            None
+        }
+    };
+
+    let state_file = std::env::var(STATE_FILE_ENV).ok();
+    let state_profile = std::env::var(STATE_PROFILE_ENV).ok();
+    let unchanged = found.as_ref().map_or_else(
+        || state_file.is_none(),
+        |found| {
+            state_file.as_deref() == Some(&*found.path.to_string_lossy())
+                && state_profile.as_deref() == Some(found.profile.as_str())
+        },
+    );
+    if unchanged {
+        if is_json() {
+            emit("hook", serde_json::json!({ "changed": false }));
+        }
+        return Ok(());
+    }
+
+    let mut statements = Vec::new();
+    let previous_keys: BTreeSet<String> = std::env::var(STATE_KEYS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .filter(|key| EnvConfig::is_valid_key(key))
+        .map(str::to_string)
+        .collect();
+    // 上次设置的变量在进入前的值
+    let originals: BTreeMap<&str, Option<String>> = previous_keys
+        .iter()
+        .map(|key| (key.as_str(), std::env::var(prev_key(key)).ok()))
+        .collect();
+    let mut restored = Vec::new();
+    let mut unset = Vec::new();
+    for (key, original) in &originals {
+        if let Some(original) = original {
+            statements.push(shell.export(key, original)?);
+            statements.push(shell.unset(&prev_key(key))?);
+            restored.push(*key);
+        } else {
+            statements.push(shell.unset(key)?);
+            unset.push(*key);
+        }
+    }
+    for key in [STATE_FILE_ENV, STATE_PROFILE_ENV, STATE_KEYS_ENV] {
+        statements.push(shell.unset(key)?);
+    }
+
+    let mut exported = Vec::new();
+    if let Some(found) = &found {
+        let vars = CccConfig::load()
+            .context("加载配置失败")
+            .and_then(|config| {
+                let profile =
+                    expand_profile(&found.profile, &config.resolve_profile(&found.profile)?)?;
+                resolve_env(&profile)
+            })
+            .with_context(|| {
+                format!(
+                    "无法使用 {} 指定的配置档案 '{}'",
+                    found.path.display(),
+                    found.profile
+                )
+            });
+        match vars {
+            Ok(vars) => {
+                for (key, value) in &vars {
+                    let original = originals
+                        .get(key.as_str())
+                        .map_or_else(|| std::env::var(key).ok(), Clone::clone);
+                    if let Some(original) = original {
+                        statements.push(shell.export(&prev_key(key), &original)?);
+                    }
+                    statements.push(shell.export(key, value)?);
+                }
+                exported = vars.into_keys().collect();
+                eprintln!(
+                    "cccrs: 使用配置档案 {} ({})",
+                    found.profile,
+                    found.path.display()
+                );
+            }
+            Err(err) => eprintln!("cccrs: {:#}", err),
+        }
+        statements.push(shell.export(STATE_FILE_ENV, &found.path.to_string_lossy())?);
+        statements.push(shell.export(STATE_PROFILE_ENV, &found.profile)?);
+        statements.push(shell.export(STATE_KEYS_ENV, &exported.join(","))?);
+    } else if let Some(previous) = &state_profile {
+        eprintln!("cccrs: 已清除配置档案 {} 的环境变量", previous);
+    }
+
+    if is_json() {
+        emit(
+            "hook",
+            serde_json::json!({
+                "changed": true,
+                "profile": found.as_ref().map(|found| &found.profile),
+                "file": found.as_ref().map(|found| &found.path),
+                "export": exported,
+                "restore": restored,
+                "unset": unset,
+            }),
+        );
+        return Ok(());
+    }
+    for statement in statements {
+        println!("{}", statement);
+    }
+    Ok(())
+}
+
+/// 记录变量原值的环境变量名
+fn prev_key(key: &str) -> String {
+    format!("{}{}", STATE_PREV_PREFIX, key)
+}
@@ -3,8 +3,8 @@
 //! 初始化 CCCRS 配置管理
 
 use crate::{
-    config::ensure_ccc_config_exists,
-    output::{info, success},
+    config::{ensure_ccc_config_exists, paths::get_ccc_config_path},
+    output::{emit, info, is_json, success},
 };
 
 /// 初始化命令
@@ -17,6 +17,12 @@ use crate::{
 pub fn execute() -> anyhow::Result<()> {
     ensure_ccc_config_exists()?;
 
+    if is_json() {
+        let config_path = get_ccc_config_path()?;
+        emit("init", serde_json::json!({ "config_path": config_path }));
+        return Ok(());
+    }
+
     info("正在初始化 CCCRS 配置管理...");
 
     success("CCCRS 配置管理已初始化");
@@ -6,7 +6,7 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{Context, bail};
 
-use crate::output::{error, info, success};
+use crate::output::{emit, error, info, is_json, success};
 
 /// 安装命令
 ///
@@ -35,6 +35,9 @@ pub fn execute() -> anyhow::Result<()> {
     // 检查写入权限
     if !is_writable(target_dir.as_path()) {
         error(&format!("需要管理员权限安装到 {}", target_dir.display()));
+        if is_json() {
+            bail!("需要管理员权限");
+        }
         println!();
         println!("请运行:");
         println!(
@@ -60,6 +63,11 @@ pub fn execute() -> anyhow::Result<()> {
         std::fs::set_permissions(&target_file, perms).context("设置权限失败")?;
     }
 
+    if is_json() {
+        emit("install", serde_json::json!({ "path": target_file }));
+        return Ok(());
+    }
+
     success(&format!("CCCRS 已成功安装到: {}", target_file.display()));
     info("现在可以在任何位置使用 'cccrs' 命令");
     println!();
@@ -2,51 +2,87 @@
 //!
 //! 查看当前配置和所有可用配置
 
+use std::path::{Path, PathBuf};
+
 use anyhow::Context;
 use colored::Colorize;
 
 use crate::{
-    config::{CccConfig, ClaudeSettings},
-    output::{info, success, warn},
+    commands::permissions::warn_insecure,
+    config::{
+        CccConfig, ClaudeSettings, ConfigLock, is_secret_env_key, mask_secret,
+        paths::SettingsScope, permissions::find_insecure_files, resolve::has_placeholder,
+    },
+    output::{emit, info, is_json, success, warn},
 };
 
 /// 列出所有配置
 ///
-/// 显示当前活跃配置（智能检测）和所有可用配置档案
+/// 显示当前活跃配置（智能检测）和所有可用配置档案。
+/// `scope` 指定用于检测的 settings 文件，只有用户级检测结果会更新当前活跃配置记录
 ///
 /// # Errors
 ///
 /// 返回配置文件读取错误
-pub fn execute() -> anyhow::Result<()> {
-    println!("=== Claude Code 配置管理 ===");
-    println!();
+pub fn execute(scope: SettingsScope) -> anyhow::Result<()> {
+    if !is_json() {
+        println!("=== Claude Code 配置管理 ===");
+        println!();
+    }
 
     // 加载配置
     let mut config = CccConfig::load().context("加载配置失败")?;
 
     // 尝试加载 Claude settings
-    let settings_result = ClaudeSettings::load();
+    let settings_path = scope.settings_path().context("获取 settings 路径失败")?;
+    let settings_result = ClaudeSettings::load_from(&settings_path);
+    if scope != SettingsScope::User {
+        info(&format!("settings 文件: {}", settings_path.display()));
+    }
 
     // 智能检测当前配置
     let detected_profile = settings_result
         .as_ref()
         .map_or(None, |settings| detect_active_profile(&config, settings));
+    let source = if detected_profile.is_some() {
+        "detected"
+    } else {
+        "recorded"
+    };
 
     // 如果检测到配置且与存储的不同，更新存储的当前配置
-    let current_profile = if let Some(detected) = &detected_profile {
+    let current_profile = if scope != SettingsScope::User {
+        detected_profile
+    } else if let Some(detected) = &detected_profile {
         if config.current.as_deref() != Some(detected) {
             info(&format!("检测到当前配置: {}", detected));
             config.current = Some(detected.clone());
-            let _ = config.save();
+            let _ = save_current(detected);
         }
         Some(detected.clone())
     } else {
         config.current.clone()
     };
 
+    // 检查失败不影响列出配置
+    let insecure = find_insecure_files().unwrap_or_default();
+
+    if is_json() {
+        let insecure_files: Vec<_> = insecure.iter().map(|file| &file.path).collect();
+        emit_list(
+            scope,
+            &settings_path,
+            &config,
+            current_profile.as_deref(),
+            source,
+            &insecure_files,
+        );
+        return Ok(());
+    }
+
     // 显示当前活跃配置
     if let Some(profile_name) = &current_profile {
-        if let Some(profile) = config.profiles.get(profile_name) {
+        if let Ok(profile) = config.resolve_profile(profile_name) {
             success(&format!("当前活跃配置: {} (智能检测)", profile_name));
             println!();
 
@@ -66,15 +102,39 @@ pub fn execute() -> anyhow::Result<()> {
             println!("  Base URL: {}", base_url);
 
             // API Key (部分显示)
-            let api_key = profile.env.anthropic_api_key.as_deref().unwrap_or("未设置");
-            let masked_key = if api_key.starts_with("sk-") {
-                "sk-***[已配置]".to_string()
-            } else if api_key != "未设置" {
-                "***[已配置]".to_string()
-            } else {
-                "未设置".to_string()
-            };
+            let masked_key = profile
+                .env
+                .anthropic_api_key
+                .as_deref()
+                .map_or_else(|| "未设置".to_string(), mask_secret);
             println!("  API Key: {}", masked_key);
+
+            // 其他环境变量
+            if !profile.env.extra.is_empty() {
+                println!("  其他环境变量:");
+                for (key, value) in &profile.env.extra {
+                    if is_secret_env_key(key) {
+                        println!("    {} = {}", key, mask_secret(value));
+                    } else {
+                        println!("    {} = {}", key, value);
+                    }
+                }
+            }
+
+            // 由 use --auto 选择时显示选择原因
+            if let Some(record) = config
+                .failover
+                .as_ref()
+                .filter(|record| &record.profile == profile_name)
+            {
+                println!(
+                    "  自动故障切换: 由 'use --auto {}' 于 {} 选择",
+                    record.group, record.checked_at
+                );
+                for skipped in &record.skipped {
+                    println!("    跳过 {}: {}", skipped.profile, skipped.reason);
+                }
+            }
             println!();
         }
     } else {
@@ -113,28 +173,101 @@ pub fn execute() -> anyhow::Result<()> {
         }
     }
 
+    if !insecure.is_empty() {
+        println!();
+        warn_insecure(&insecure);
+    }
+
     Ok(())
 }
 
+/// 输出 JSON 格式的配置列表（不包含敏感信息）
+fn emit_list(
+    scope: SettingsScope,
+    settings_path: &Path,
+    config: &CccConfig,
+    current: Option<&str>,
+    source: &str,
+    insecure_files: &[&PathBuf],
+) {
+    let active = current
+        .filter(|name| config.has_profile(name))
+        .map(|name| serde_json::json!({ "name": name, "source": source }));
+    let mut names: Vec<_> = config.profiles.keys().collect();
+    names.sort();
+    let profiles: Vec<_> = names
+        .into_iter()
+        .filter_map(|name| {
+            let extends = config.get_profile(name)?.extends.clone();
+            let profile = config.resolve_profile(name).ok()?;
+            Some((name, extends, profile))
+        })
+        .map(|(name, extends, profile)| {
+            serde_json::json!({
+                "name": name,
+                "active": current == Some(name.as_str()),
+                "extends": extends,
+                "base_url": profile.env.anthropic_base_url,
+                "has_api_key_helper": profile.api_key_helper.is_some(),
+                "env_keys": profile.env.vars().into_keys().collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    emit(
+        "list",
+        serde_json::json!({
+            "scope": scope,
+            "settings_path": settings_path,
+            "active": active,
+            "profiles": profiles,
+            "insecure_files": insecure_files,
+            "failover": config.failover,
+        }),
+    );
+}
+
+/// 在配置锁保护下记录当前活跃配置
+fn save_current(name: &str) -> anyhow::Result<()> {
+    let _lock = ConfigLock::acquire()?;
+    let mut latest = CccConfig::load()?;
+    latest.current = Some(name.to_string());
+    latest.save()
+}
+
 /// 智能检测当前活跃的配置
 ///
-/// 通过比较 settings.json 和 cccrs-config.json 中的配置来检测
+/// 通过比较 settings.json 和 cccrs-config.json 中的配置来检测：
+/// 配置档案（解析继承关系后）中的所有环境变量都与 settings.json 一致时视为匹配
+/// （包含占位符的字段不参与比较），
+/// 多个档案同时匹配时选择环境变量最多的一个
 #[must_use]
 fn detect_active_profile(config: &CccConfig, settings: &ClaudeSettings) -> Option<String> {
     // 获取当前 settings 中的配置
-    let settings_url = settings.env.as_ref()?.anthropic_base_url.as_ref()?;
-    let settings_key = settings.env.as_ref()?.anthropic_api_key.as_ref()?;
+    let settings_vars = settings.env.as_ref()?.vars();
 
     // 遍历所有配置档案进行比较
-    for (name, profile) in &config.profiles {
-        let profile_url = profile.env.anthropic_base_url.as_ref()?;
-        let profile_key = profile.env.anthropic_api_key.as_ref()?;
-
-        // 比较核心字段是否匹配
-        if profile_url == settings_url && profile_key == settings_key {
-            return Some(name.clone());
-        }
-    }
-
-    None
+    let resolved: Vec<_> = config
+        .profiles
+        .keys()
+        .filter_map(|name| Some((name, config.resolve_profile(name).ok()?)))
+        .collect();
+    resolved
+        .iter()
+        .filter(|(_, profile)| !profile.env.is_empty())
+        .map(|(name, profile)| (name, profile.env.vars()))
+        .filter(|(_, vars)| {
+            // 占位符在写入时才展开，只比较不含占位符的字段
+            vars.values().any(|value| !has_placeholder(value))
+                && vars.iter().all(|(key, value)| {
+                    has_placeholder(value)
+                        || settings_vars.get(*key).map(String::as_str) == Some(*value)
+                })
+        })
+        .max_by(|(a_name, a_vars), (b_name, b_vars)| {
+            a_vars
+                .len()
+                .cmp(&b_vars.len())
+                .then_with(|| b_name.cmp(a_name))
+        })
+        .map(|(name, _)| (*name).clone())
 }
@@ -0,0 +1,61 @@
+//! Permissions 命令
+//!
+//! 检查并修复包含 API Key 的文件的权限
+
+use crate::{
+    config::permissions::{InsecureFile, find_insecure_files, fix_permissions},
+    output::{emit, is_json, success, warn},
+};
+
+/// 检查敏感文件权限，`fix` 为 `true` 时将可被其他用户访问的文件修改为 0600
+///
+/// # Errors
+///
+/// 返回文件元数据读取或权限修改错误
+pub fn execute(fix: bool) -> anyhow::Result<()> {
+    let insecure = find_insecure_files()?;
+    if fix {
+        fix_permissions(&insecure)?;
+    }
+
+    if is_json() {
+        let files: Vec<_> = insecure
+            .iter()
+            .map(|file| serde_json::json!({ "path": file.path, "mode": format_mode(file.mode) }))
+            .collect();
+        emit(
+            "permissions",
+            serde_json::json!({ "insecure": files, "fixed": fix && !insecure.is_empty() }),
+        );
+        return Ok(());
+    }
+
+    if insecure.is_empty() {
+        success("所有包含 API Key 的文件权限均为 600");
+        return Ok(());
+    }
+    for file in &insecure {
+        println!("  {} {}", format_mode(file.mode), file.path.display());
+    }
+    if fix {
+        success(&format!("已将 {} 个文件的权限修改为 600", insecure.len()));
+    } else {
+        warn_insecure(&insecure);
+    }
+
+    Ok(())
+}
+
+/// 提示存在可被其他用户读取的敏感文件
+pub fn warn_insecure(insecure: &[InsecureFile]) {
+    warn(&format!(
+        "{} 个包含 API Key 的文件可被其他用户访问，运行 'cccrs permissions --fix' 修复",
+        insecure.len()
+    ));
+}
+
+/// 格式化权限位，例如 `644`
+#[must_use]
+fn format_mode(mode: u32) -> String {
+    format!("{:03o}", mode)
+}
@@ -0,0 +1,108 @@
+//! Proxy 命令
+//!
+//! 启动本地路由代理，切换代理转发的目标配置档案
+
+use anyhow::Context;
+use chrono::Local;
+use colored::Colorize;
+
+use crate::{
+    config::{CccConfig, ConfigLock},
+    error::CccError,
+    output::{emit, info, is_json, success, warn},
+    proxy::{ProxyServer, RequestLog},
+};
+
+/// 在 `127.0.0.1:<port>` 启动本地路由代理（前台运行）
+///
+/// 代理把请求转发到代理目标（未设置时为当前活跃配置）的 `ANTHROPIC_BASE_URL` 并注入其凭据，
+/// 目标不可用时依次尝试其备用配置。指定 `profile` 时先将其设置为代理目标
+///
+/// # Errors
+///
+/// 返回配置档案不存在、端口被占用等错误
+pub fn start(port: u16, profile: Option<&str>) -> anyhow::Result<()> {
+    if let Some(name) = profile {
+        set_target(name)?;
+    }
+    let server = ProxyServer::bind(port)?;
+    let base_url = format!("http://{}", server.local_addr());
+
+    let config = CccConfig::load().context("加载配置失败")?;
+    let target = config.proxy_target.or(config.current);
+    emit(
+        "proxy start",
+        serde_json::json!({ "base_url": base_url, "target": target }),
+    );
+    success(&format!("代理已启动: {}", base_url));
+    match &target {
+        Some(target) => info(&format!(
+            "代理目标: {} (使用 cccrs proxy switch <name> 切换)",
+            target
+        )),
+        None => warn("未设置代理目标，请运行 cccrs proxy switch <name>"),
+    }
+    info(&format!(
+        "将 Claude Code 的 ANTHROPIC_BASE_URL 设置为 {} 即可通过代理访问",
+        base_url
+    ));
+
+    server.run(log_request);
+    Ok(())
+}
+
+/// 切换代理目标，运行中的代理从下一个请求开始生效
+///
+/// # Errors
+///
+/// 返回配置档案不存在、配置文件读写错误
+pub fn switch(name: &str) -> anyhow::Result<()> {
+    set_target(name)?;
+    emit("proxy switch", serde_json::json!({ "target": name }));
+    success(&format!("代理目标已切换到: {}", name));
+    Ok(())
+}
+
+/// 记录代理目标
+fn set_target(name: &str) -> anyhow::Result<()> {
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
+    if !config.has_profile(name) {
+        return Err(CccError::ProfileNotFound(name.to_string()).into());
+    }
+    // 提前检查备用配置，避免代理运行时才发现
+    config.fallback_candidates(name)?;
+    config.proxy_target = Some(name.to_string());
+    config.save().context("保存配置失败")
+}
+
+/// 输出单个请求的转发记录（不包含请求内容和凭据）
+fn log_request(entry: &RequestLog) {
+    if is_json() {
+        emit("proxy request", serde_json::json!(entry));
+        return;
+    }
+    let text = entry
+        .status
+        .map_or_else(|| "---".to_string(), |status| status.to_string());
+    let status = if entry.status.is_some_and(|code| code < 400) {
+        text.green()
+    } else {
+        text.red()
+    };
+    println!(
+        "[{}] {} {} -> {} {} {}ms",
+        Local::now().format("%H:%M:%S"),
+        entry.method,
+        entry.path,
+        entry.profile.as_deref().unwrap_or("-"),
+        status,
+        entry.latency_ms
+    );
+    for skipped in &entry.skipped {
+        println!("    跳过 {}: {}", skipped.profile, skipped.reason);
+    }
+    if let Some(error) = &entry.error {
+        println!("    {}", error);
+    }
+}
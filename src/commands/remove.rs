@@ -5,8 +5,9 @@
 use anyhow::Context;
 
 use crate::{
-    config::CccConfig,
-    output::{success, warn},
+    config::{CccConfig, ConfigLock},
+    error::CccError,
+    output::{emit, success, warn},
 };
 
 /// 删除配置档案
@@ -15,12 +16,31 @@ use crate::{
 ///
 /// # Errors
 ///
-/// 返回配置档案不存在等错误
+/// 返回配置档案不存在、配置档案被其他配置继承或列为备用配置等错误
 pub fn execute(name: &str) -> anyhow::Result<()> {
+    let _lock = ConfigLock::acquire()?;
     let mut config = CccConfig::load().context("加载配置失败")?;
 
     if !config.has_profile(name) {
-        anyhow::bail!("配置档案 '{}' 不存在", name);
+        return Err(CccError::ProfileNotFound(name.to_string()).into());
+    }
+
+    let children = config.children_of(name);
+    if !children.is_empty() {
+        anyhow::bail!(
+            "配置档案 '{}' 被 {} 继承，请先修改或删除这些配置",
+            name,
+            children.join(", ")
+        );
+    }
+
+    let referrers = config.fallback_referrers(name);
+    if !referrers.is_empty() {
+        anyhow::bail!(
+            "配置档案 '{}' 是 {} 的备用配置，请先修改这些配置的 fallback",
+            name,
+            referrers.join(", ")
+        );
     }
 
     // 检查是否是当前配置
@@ -33,12 +53,23 @@ pub fn execute(name: &str) -> anyhow::Result<()> {
     if is_current {
         config.current = None;
     }
+    let was_proxy_target = config.proxy_target.as_deref() == Some(name);
+    if was_proxy_target {
+        config.proxy_target = None;
+    }
 
     config.save().context("保存配置失败")?;
 
+    emit(
+        "remove",
+        serde_json::json!({ "profile": name, "was_current": is_current }),
+    );
     if is_current {
         warn("已删除当前活跃配置，请使用 'ccc use <name>' 切换到其他配置");
     }
+    if was_proxy_target {
+        warn("已删除代理目标，请使用 'cccrs proxy switch <name>' 切换到其他配置");
+    }
 
     success(&format!("配置档案 '{}' 已删除", name));
 
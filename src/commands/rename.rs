@@ -0,0 +1,55 @@
+//! Rename 命令
+//!
+//! 重命名配置档案
+
+use anyhow::Context;
+
+use crate::{
+    config::{CccConfig, ConfigLock, validate_profile_name},
+    error::CccError,
+    output::{emit, success, warn},
+};
+
+/// 重命名配置档案
+///
+/// 重命名当前活跃配置时会同步更新记录。`new` 已存在时需指定 `force` 才会覆盖
+///
+/// # Errors
+///
+/// 返回配置名称无效、配置档案不存在、目标配置已存在等错误
+pub fn execute(old: &str, new: &str, force: bool) -> anyhow::Result<()> {
+    if !validate_profile_name(new) {
+        return Err(CccError::InvalidProfileName(new.to_string()).into());
+    }
+    if old == new {
+        anyhow::bail!("新旧配置名称相同");
+    }
+
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
+
+    if !config.has_profile(old) {
+        return Err(CccError::ProfileNotFound(old.to_string()).into());
+    }
+    let overwritten = config.has_profile(new);
+    if overwritten && !force {
+        return Err(CccError::ProfileExists(new.to_string()).into());
+    }
+    let overwrote_current = overwritten && config.current.as_deref() == Some(new);
+
+    let _ = config.rename_profile(old, new);
+    config.save().context("保存配置失败")?;
+
+    emit(
+        "rename",
+        serde_json::json!({ "from": old, "to": new, "overwritten": overwritten }),
+    );
+    if overwrote_current {
+        warn("已覆盖当前活跃配置，请使用 'ccc use <name>' 重新切换配置");
+    } else if overwritten {
+        warn(&format!("已覆盖配置档案 '{}'", new));
+    }
+    success(&format!("配置档案 '{}' 已重命名为 '{}'", old, new));
+
+    Ok(())
+}
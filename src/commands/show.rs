@@ -0,0 +1,150 @@
+//! Show 命令
+//!
+//! 查看配置档案的详细信息
+
+use anyhow::Context;
+use colored::Colorize;
+
+use crate::{
+    config::{
+        CccConfig, ClaudeSettings, Profile, is_secret_env_key, mask_secret, paths::SettingsScope,
+    },
+    error::CccError,
+    output::{emit, is_json, warn},
+};
+
+/// 显示配置档案的所有字段
+///
+/// 敏感字段（API Key、Token、`apiKeyHelper` 等）默认只显示首尾少量字符，`reveal` 为 `true` 时显示原文。
+/// 配置档案继承其他配置时，同时显示原始字段和解析继承后的结果。
+/// 最后显示解析结果是否与 `scope` 对应的 settings 文件一致
+///
+/// # Errors
+///
+/// 返回配置档案不存在、继承存在循环、配置文件读取错误
+pub fn execute(name: &str, reveal: bool, scope: SettingsScope) -> anyhow::Result<()> {
+    let config = CccConfig::load().context("加载配置失败")?;
+    let raw = config
+        .get_profile(name)
+        .ok_or_else(|| CccError::ProfileNotFound(name.to_string()))?;
+    let resolved = config.resolve_profile(name)?;
+    let chain = config.extends_chain(name)?;
+
+    let settings_path = scope.settings_path().context("获取 settings 路径失败")?;
+    let mismatched = ClaudeSettings::load_from(&settings_path)
+        .ok()
+        .map(|settings| settings.mismatched_fields(&resolved));
+    let is_current = config.current.as_deref() == Some(name);
+
+    let display = |key: &str, value: &str| {
+        if reveal || !is_secret_env_key(key) {
+            value.to_string()
+        } else {
+            mask_secret(value)
+        }
+    };
+
+    if is_json() {
+        emit(
+            "show",
+            serde_json::json!({
+                "name": name,
+                "current": is_current,
+                "revealed": reveal,
+                "extends": raw.extends,
+                "fallback": raw.fallback,
+                "raw": profile_json(raw, &display),
+                "resolved": profile_json(&resolved, &display),
+                "settings_path": settings_path,
+                "matches_settings": mismatched.as_ref().map(Vec::is_empty),
+                "mismatched": mismatched,
+            }),
+        );
+        return Ok(());
+    }
+
+    if is_current {
+        println!("配置档案: {} (当前)", name.green());
+    } else {
+        println!("配置档案: {}", name);
+    }
+    println!();
+
+    if raw.extends.is_some() {
+        println!("  继承: {}", chain.join(" -> "));
+        println!();
+        println!("  原始字段:");
+        print_profile(raw, &display, "    ");
+        println!();
+        println!("  解析结果:");
+        print_profile(&resolved, &display, "    ");
+    } else {
+        print_profile(raw, &display, "  ");
+    }
+    if !raw.fallback.is_empty() {
+        println!();
+        println!("  备用配置: {}", raw.fallback.join(" -> "));
+    }
+    println!();
+
+    match &mismatched {
+        None => warn(&format!(
+            "无法读取 settings 文件: {}",
+            settings_path.display()
+        )),
+        Some(fields) if fields.is_empty() => {
+            println!("  {} 与 {} 一致", "✓".green(), settings_path.display());
+        }
+        Some(fields) => {
+            println!(
+                "  {} 与 {} 不一致: {}",
+                "✗".yellow(),
+                settings_path.display(),
+                fields.join(", ")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// 将配置档案字段转换为 JSON（敏感字段按 `display` 处理）
+#[must_use]
+fn profile_json(profile: &Profile, display: &impl Fn(&str, &str) -> String) -> serde_json::Value {
+    let env: serde_json::Map<_, _> = profile
+        .env
+        .vars()
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), display(key, value).into()))
+        .collect();
+    serde_json::json!({
+        "api_key_helper": profile
+            .api_key_helper
+            .as_deref()
+            .map(|helper| display("apiKeyHelper", helper)),
+        "env": env,
+    })
+}
+
+/// 显示配置档案的 API Key Helper 和环境变量
+fn print_profile(profile: &Profile, display: &impl Fn(&str, &str) -> String, indent: &str) {
+    let helper = profile
+        .api_key_helper
+        .as_deref()
+        .map(|helper| display("apiKeyHelper", helper));
+    println!(
+        "{}API Key Helper: {}",
+        indent,
+        helper.as_deref().unwrap_or("未设置")
+    );
+
+    let vars = profile.env.vars();
+    if vars.is_empty() {
+        println!("{}环境变量: 未设置", indent);
+        return;
+    }
+    println!("{}环境变量:", indent);
+    for (key, value) in vars {
+        println!("{}  {} = {}", indent, key, display(key, value));
+    }
+}
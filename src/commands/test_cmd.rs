@@ -0,0 +1,112 @@
+//! Test 命令
+//!
+//! 检查配置档案的端点是否可达、凭据是否有效
+
+use std::{process::ExitCode, time::Duration};
+
+use anyhow::Context;
+use colored::Colorize;
+
+use crate::{
+    config::CccConfig,
+    error::CccError,
+    output::{emit, is_json},
+    probe::{Outcome, ProbeResult, probe_profile},
+};
+
+/// 向配置档案的 `ANTHROPIC_BASE_URL` 发送最小的认证请求，报告 HTTP 状态码、耗时和认证错误
+///
+/// `name` 为 `None` 时检查所有配置档案；存在失败的配置时以失败状态码退出
+///
+/// # Errors
+///
+/// 返回配置档案不存在、配置文件读取错误
+pub fn execute(name: Option<&str>, timeout: u64) -> anyhow::Result<ExitCode> {
+    let config = CccConfig::load().context("加载配置失败")?;
+    let names: Vec<String> = if let Some(name) = name {
+        if !config.has_profile(name) {
+            return Err(CccError::ProfileNotFound(name.to_string()).into());
+        }
+        vec![name.to_string()]
+    } else {
+        let mut names: Vec<_> = config.profiles.keys().cloned().collect();
+        names.sort();
+        names
+    };
+
+    let timeout = Duration::from_secs(timeout);
+    let results: Vec<_> = names
+        .iter()
+        .map(|profile_name| {
+            let result = probe_profile(&config, profile_name, timeout);
+            if !is_json() {
+                print_result(profile_name, &result);
+            }
+            (profile_name, result)
+        })
+        .collect();
+
+    let failed = results.iter().filter(|(_, result)| !is_ok(result)).count();
+    if is_json() {
+        let results: Vec<_> = results
+            .iter()
+            .map(|(profile_name, result)| match result {
+                Ok(probe) => serde_json::json!({
+                    "profile": profile_name,
+                    "ok": probe.is_ok(),
+                    "outcome": probe.outcome,
+                    "status": probe.status,
+                    "latency_ms": probe.latency_ms,
+                    "url": probe.url,
+                    "message": probe.message,
+                }),
+                Err(err) => serde_json::json!({
+                    "profile": profile_name,
+                    "ok": false,
+                    "outcome": "config_error",
+                    "message": format!("{:#}", err),
+                }),
+            })
+            .collect();
+        emit("test", serde_json::json!({ "results": results }));
+    } else if results.len() > 1 {
+        println!();
+        println!("{} 个配置可用，{} 个失败", results.len() - failed, failed);
+    }
+
+    Ok(if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// 探测是否成功
+fn is_ok(result: &anyhow::Result<ProbeResult>) -> bool {
+    result.as_ref().is_ok_and(ProbeResult::is_ok)
+}
+
+/// 输出单个配置档案的探测结果
+fn print_result(name: &str, result: &anyhow::Result<ProbeResult>) {
+    let probe = match result {
+        Ok(probe) => probe,
+        Err(err) => {
+            println!("{} {}  配置错误: {:#}", "✗".red(), name, err);
+            return;
+        }
+    };
+    let status = probe
+        .status
+        .map_or_else(|| "---".to_string(), |status| status.to_string());
+    let summary = format!(
+        "{}  {}  {}ms  {}",
+        name, status, probe.latency_ms, probe.url
+    );
+    let message = probe.message.as_deref().unwrap_or_default();
+    match probe.outcome {
+        Outcome::Ok => println!("{} {}", "✓".green(), summary),
+        Outcome::AuthError => println!("{} {}\n    认证失败: {}", "✗".red(), summary, message),
+        Outcome::HttpError => println!("{} {}\n    请求失败: {}", "✗".red(), summary, message),
+        Outcome::Unreachable => println!("{} {}\n    无法连接: {}", "✗".red(), summary, message),
+    }
+}
@@ -2,67 +2,259 @@
 //!
 //! 切换到指定配置档案
 
+use std::{path::PathBuf, time::Duration};
+
 use anyhow::Context;
+use chrono::{Local, SecondsFormat};
 
 use crate::{
-    config::{CccConfig, ClaudeSettings},
-    output::{info, success},
+    config::{
+        CccConfig, ClaudeEnv, ClaudeSettings, ConfigLock, FailoverRecord, ManagedKeys,
+        SkippedCandidate, backup::backup_settings, expand_profile, is_secret_env_key,
+        paths::SettingsScope,
+    },
+    error::CccError,
+    output::{emit, info, success, warn},
+    probe::probe_profile,
 };
 
 /// 切换到指定配置档案
 ///
-/// 会自动备份当前的 settings.json，然后更新配置
+/// 会自动备份当前的 settings.json，然后更新配置。
+/// `clean` 为 `true` 时，会移除之前由 cccrs 写入、但新配置档案未定义的字段
+/// （如 `apiKeyHelper`、环境变量），用户自行添加的字段保持不变。
+/// `scope` 指定写入用户级、项目级或项目本地 settings 文件，项目级 settings 不会自动备份，
+/// 也不会更新当前活跃配置记录
 ///
 /// # Errors
 ///
 /// 返回配置档案不存在、文件操作失败等错误
-pub fn execute(name: &str) -> anyhow::Result<()> {
-    let config = CccConfig::load().context("加载配置失败")?;
+pub fn execute(name: &str, clean: bool, scope: SettingsScope) -> anyhow::Result<()> {
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
 
-    let profile = config
-        .get_profile(name)
-        .ok_or_else(|| anyhow::anyhow!("配置档案 '{}' 不存在", name))?
-        .clone();
+    let applied = apply_profile(&mut config, name, clean, scope, &ManagedKeys::default())?;
+    if scope == SettingsScope::User {
+        config.failover = None;
+    }
+    config.save().context("保存配置失败")?;
 
-    // 加载并备份当前设置
-    let mut settings = ClaudeSettings::load().context("加载 Claude settings 失败")?;
+    emit(
+        "use",
+        serde_json::json!({
+            "profile": name,
+            "scope": scope,
+            "settings_path": applied.settings_path,
+            "backup_path": applied.backup_path,
+            "removed": describe_keys(&applied.removed),
+        }),
+    );
+    if scope == SettingsScope::User {
+        success(&format!("已切换到配置档案: {}", name));
+    } else {
+        success(&format!(
+            "已切换到配置档案: {} ({})",
+            name,
+            applied.settings_path.display()
+        ));
+    }
 
-    // 备份
-    let backup_path = settings.backup().context("备份失败")?;
-    info(&format!("已备份当前配置: {}", backup_path.display()));
+    Ok(())
+}
 
-    // 只更新相关字段
-    if let Some(helper) = &profile.api_key_helper {
-        settings.api_key_helper = Some(helper.clone());
+/// 依次检查配置档案及其备用配置（`fallback`）的端点，切换到第一个可用的配置档案
+///
+/// 检查方式与 `test` 命令相同。写入用户级 settings 时，选择结果（包括被跳过的候选及原因）
+/// 记录在配置文件中
+///
+/// # Errors
+///
+/// 返回配置档案或备用配置不存在、所有候选均不可用、文件操作失败等错误
+pub fn execute_auto(
+    group: &str,
+    clean: bool,
+    scope: SettingsScope,
+    timeout: u64,
+) -> anyhow::Result<()> {
+    // 检查端点可能耗时较长，不持有配置锁
+    let config = CccConfig::load().context("加载配置失败")?;
+    let candidates = config.fallback_candidates(group)?;
+
+    let timeout = Duration::from_secs(timeout);
+    let mut skipped: Vec<SkippedCandidate> = Vec::new();
+    let mut chosen = None;
+    for candidate in candidates {
+        let reason = match probe_profile(&config, candidate, timeout) {
+            Ok(result) => result.failure_reason(),
+            Err(err) => Some(format!("配置错误: {:#}", err)),
+        };
+        let Some(reason) = reason else {
+            chosen = Some(candidate.to_string());
+            break;
+        };
+        warn(&format!("配置档案 '{}' 不可用: {}", candidate, reason));
+        skipped.push(SkippedCandidate {
+            profile: candidate.to_string(),
+            reason,
+        });
     }
+    let Some(chosen) = chosen else {
+        let reasons: Vec<_> = skipped
+            .iter()
+            .map(|skipped| format!("{} ({})", skipped.profile, skipped.reason))
+            .collect();
+        return Err(CccError::NoHealthyProfile(reasons.join("; ")).into());
+    };
 
-    // 更新 env 字段
-    if let Some(url) = &profile.env.anthropic_base_url {
-        if settings.env.is_none() {
-            settings.env = Some(crate::config::ClaudeEnv::default());
-        }
-        if let Some(env) = settings.env.as_mut() {
-            env.anthropic_base_url = Some(url.clone());
-        }
+    let _lock = ConfigLock::acquire()?;
+    let mut latest = CccConfig::load().context("加载配置失败")?;
+    let applied = apply_profile(&mut latest, &chosen, clean, scope, &ManagedKeys::default())?;
+    let record = FailoverRecord {
+        group: group.to_string(),
+        profile: chosen.clone(),
+        checked_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        skipped,
+    };
+    if scope == SettingsScope::User {
+        latest.failover = Some(record.clone());
     }
+    latest.save().context("保存配置失败")?;
 
-    if let Some(key) = &profile.env.anthropic_api_key {
-        if settings.env.is_none() {
-            settings.env = Some(crate::config::ClaudeEnv::default());
-        }
-        if let Some(env) = settings.env.as_mut() {
-            env.anthropic_api_key = Some(key.clone());
-        }
+    emit(
+        "use",
+        serde_json::json!({
+            "profile": chosen,
+            "scope": scope,
+            "settings_path": applied.settings_path,
+            "backup_path": applied.backup_path,
+            "removed": describe_keys(&applied.removed),
+            "failover": record,
+        }),
+    );
+    if record.skipped.is_empty() {
+        success(&format!("配置档案 '{}' 可用，已切换到该配置", chosen));
+    } else {
+        success(&format!(
+            "配置档案 '{}' 不可用，已切换到备用配置: {}",
+            group, chosen
+        ));
     }
 
-    settings.save().context("保存设置失败")?;
+    Ok(())
+}
 
-    // 更新当前配置记录
-    let mut updated_config = CccConfig::load().context("加载配置失败")?;
-    updated_config.current = Some(name.to_string());
-    updated_config.save().context("保存配置失败")?;
+/// 写入配置档案的结果
+#[derive(Debug, Clone)]
+pub struct Applied {
+    /// 写入的 settings 文件路径
+    pub settings_path: PathBuf,
+    /// 写入前的备份路径（仅用户级 settings）
+    pub backup_path: Option<PathBuf>,
+    /// 从 settings 中移除的字段
+    pub removed: ManagedKeys,
+}
 
-    success(&format!("已切换到配置档案: {}", name));
+/// 将配置档案（解析继承关系并展开占位符后）写入 settings 并更新 `config` 中的记录
+///
+/// 调用方需持有配置锁，并在之后保存 `config`。
+/// `removed` 为额外需要移除的字段（例如编辑配置档案时删除的字段），不受 `clean` 影响
+///
+/// # Errors
+///
+/// 返回配置档案不存在、继承存在循环、占位符解析失败、文件操作失败等错误
+pub fn apply_profile(
+    config: &mut CccConfig,
+    name: &str,
+    clean: bool,
+    scope: SettingsScope,
+    removed: &ManagedKeys,
+) -> anyhow::Result<Applied> {
+    let profile = expand_profile(name, &config.resolve_profile(name)?)?;
 
-    Ok(())
+    let settings_path = scope.settings_path().context("获取 settings 路径失败")?;
+    let project_settings = (scope != SettingsScope::User).then_some(settings_path.as_path());
+
+    let backup_path = if project_settings.is_none() {
+        // 确认当前设置可以解析，再备份
+        ClaudeSettings::load().context("加载 Claude settings 失败")?;
+        let backup_path = backup_settings(None).context("备份失败")?;
+        info(&format!("已备份当前配置: {}", backup_path.display()));
+        Some(backup_path)
+    } else {
+        None
+    };
+
+    if scope == SettingsScope::Project
+        && profile.env.vars().keys().any(|key| is_secret_env_key(key))
+    {
+        warn(
+            "项目级 settings.json 通常会提交到版本库，建议使用 --scope local 存放 API Key 等敏感信息",
+        );
+    }
+
+    // 之前由 cccrs 写入的字段：历史记录加上当前配置档案定义的字段
+    let recorded = config.managed_keys(project_settings);
+    let previous = match (project_settings, config.current.as_deref()) {
+        (None, Some(current)) => config.resolve_profile(current).map_or_else(
+            |_| recorded.clone(),
+            |current| recorded.union(&ManagedKeys::from_profile(&current)),
+        ),
+        _ => recorded,
+    };
+    let incoming = ManagedKeys::from_profile(&profile);
+
+    // 只更新配置档案定义的字段
+    let updated = ClaudeSettings {
+        api_key_helper: profile.api_key_helper.clone(),
+        env: (!profile.env.is_empty()).then(|| ClaudeEnv::from(&profile.env)),
+    };
+
+    let stale = if clean {
+        previous.difference(&incoming)
+    } else {
+        removed.difference(&incoming)
+    };
+    updated
+        .save_clean_to(scope, &stale)
+        .context("保存设置失败")?;
+    if !stale.is_empty() {
+        info(&format!(
+            "已移除过期字段: {}",
+            describe_keys(&stale).join(", ")
+        ));
+    }
+    let managed = if clean {
+        incoming
+    } else {
+        previous.union(&incoming).difference(&stale)
+    };
+
+    // 更新当前配置记录，切换到其他配置后自动故障切换的记录不再适用
+    if project_settings.is_none() {
+        config.current = Some(name.to_string());
+        if config
+            .failover
+            .as_ref()
+            .is_some_and(|record| record.profile != name)
+        {
+            config.failover = None;
+        }
+    }
+    config.set_managed_keys(project_settings, managed);
+
+    Ok(Applied {
+        settings_path,
+        backup_path,
+        removed: stale,
+    })
+}
+
+/// 将字段记录格式化为可读的字段列表
+#[must_use]
+pub fn describe_keys(keys: &ManagedKeys) -> Vec<String> {
+    keys.api_key_helper
+        .then(|| "apiKeyHelper".to_string())
+        .into_iter()
+        .chain(keys.env.iter().map(|key| format!("env.{}", key)))
+        .collect()
 }
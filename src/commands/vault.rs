@@ -0,0 +1,212 @@
+//! Vault 命令
+//!
+//! 在明文配置文件和加密存储之间迁移配置档案的敏感字段
+
+use anyhow::Context;
+
+use crate::{
+    config::{
+        CccConfig, ConfigLock, is_secret_env_key,
+        paths::get_vault_path,
+        resolve::has_placeholder,
+        vault::{Vault, read_passphrase, reference, referenced_ids},
+    },
+    error::CccError,
+    output::{emit, info, is_json, success, warn},
+};
+
+/// 将配置文件中明文保存的敏感字段迁移到加密存储
+///
+/// 敏感字段（名称包含 KEY、TOKEN、SECRET 等的环境变量）会被替换为 `${vault:ID}` 占位符。
+/// 加密存储不存在时会创建并要求设置口令，已存在时使用原口令解锁后追加
+///
+/// # Errors
+///
+/// 返回口令错误、加密存储或配置文件读写错误
+pub fn encrypt() -> anyhow::Result<()> {
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
+
+    let plaintext = plaintext_secrets(&config);
+    if plaintext.is_empty() {
+        emit(
+            "vault encrypt",
+            serde_json::json!({ "vault_path": get_vault_path()?, "encrypted": [] }),
+        );
+        info("没有需要加密的明文敏感字段");
+        return Ok(());
+    }
+
+    let mut vault = if Vault::exists()? {
+        Vault::unlock(&read_passphrase(false)?)?
+    } else {
+        Vault::create(&read_passphrase(true)?)?
+    };
+
+    let mut encrypted = Vec::new();
+    for (name, key) in plaintext {
+        let Some(profile) = config.profiles.get_mut(&name) else {
+            continue;
+        };
+        let Some(value) = profile.env.get(&key).map(str::to_string) else {
+            continue;
+        };
+        let id = vault.insert(&format!("{}.{}", name, key), value);
+        profile.env.set(&key, reference(&id));
+        encrypted.push(serde_json::json!({ "profile": name, "key": key, "id": id }));
+    }
+
+    // 先写入加密存储，保证配置文件中的占位符始终可以解析
+    vault.save()?;
+    config.save().context("保存配置失败")?;
+
+    emit(
+        "vault encrypt",
+        serde_json::json!({ "vault_path": vault.path(), "encrypted": encrypted }),
+    );
+    success(&format!(
+        "已将 {} 个敏感字段加密保存到 {}",
+        encrypted.len(),
+        vault.path().display()
+    ));
+    info("之后新增的敏感字段仍以明文保存，可再次运行 cccrs vault encrypt 加密");
+
+    Ok(())
+}
+
+/// 将加密存储中的敏感字段还原到配置文件，并删除加密存储
+///
+/// # Errors
+///
+/// 返回加密存储不存在、口令错误、配置文件读写错误
+pub fn decrypt() -> anyhow::Result<()> {
+    let _lock = ConfigLock::acquire()?;
+    let mut config = CccConfig::load().context("加载配置失败")?;
+
+    if !Vault::exists()? {
+        return Err(CccError::VaultNotFound.into());
+    }
+    let vault = Vault::unlock(&read_passphrase(false)?)?;
+
+    let mut decrypted = Vec::new();
+    for (name, key) in vault_references(&config) {
+        let Some(profile) = config.profiles.get_mut(&name) else {
+            continue;
+        };
+        let Some(value) = profile.env.get(&key) else {
+            continue;
+        };
+        let revealed = vault
+            .reveal(value)
+            .with_context(|| format!("解析配置档案 '{}' 的字段 '{}' 失败", name, key))?;
+        profile.env.set(&key, revealed);
+        decrypted.push(serde_json::json!({ "profile": name, "key": key }));
+    }
+
+    // 先写入配置文件，再删除加密存储
+    config.save().context("保存配置失败")?;
+    std::fs::remove_file(vault.path()).context("删除加密存储失败")?;
+
+    emit(
+        "vault decrypt",
+        serde_json::json!({ "vault_path": vault.path(), "decrypted": decrypted }),
+    );
+    success(&format!(
+        "已将 {} 个敏感字段还原为明文，并删除加密存储",
+        decrypted.len()
+    ));
+    warn("API Key 现在以明文保存在配置文件中");
+
+    Ok(())
+}
+
+/// 查看加密存储状态（不需要口令）
+///
+/// # Errors
+///
+/// 返回配置文件读取错误
+pub fn status() -> anyhow::Result<()> {
+    let config = CccConfig::load().context("加载配置失败")?;
+    let vault_path = get_vault_path()?;
+    let enabled = vault_path.exists();
+    let encrypted = vault_references(&config);
+    let plaintext = plaintext_secrets(&config);
+
+    if is_json() {
+        let fields = |fields: &[(String, String)]| -> Vec<serde_json::Value> {
+            fields
+                .iter()
+                .map(|(name, key)| serde_json::json!({ "profile": name, "key": key }))
+                .collect()
+        };
+        emit(
+            "vault status",
+            serde_json::json!({
+                "enabled": enabled,
+                "vault_path": vault_path,
+                "encrypted": fields(&encrypted),
+                "plaintext": fields(&plaintext),
+            }),
+        );
+        return Ok(());
+    }
+
+    if enabled {
+        println!("加密存储: {}", vault_path.display());
+    } else {
+        println!("加密存储: 未启用");
+    }
+    println!("  已加密字段: {}", encrypted.len());
+    for (name, key) in &encrypted {
+        println!("    {}.{}", name, key);
+    }
+    println!("  明文敏感字段: {}", plaintext.len());
+    for (name, key) in &plaintext {
+        println!("    {}.{}", name, key);
+    }
+    if !plaintext.is_empty() {
+        println!();
+        info("运行 cccrs vault encrypt 加密明文敏感字段");
+    }
+
+    Ok(())
+}
+
+/// 列出以明文保存的敏感字段 `(配置名称, 字段名)`，按名称排序
+///
+/// 使用占位符引用外部值的字段不算明文
+#[must_use]
+fn plaintext_secrets(config: &CccConfig) -> Vec<(String, String)> {
+    collect_fields(config, |key, value| {
+        is_secret_env_key(key) && !has_placeholder(value)
+    })
+}
+
+/// 列出引用加密存储的字段 `(配置名称, 字段名)`，按名称排序
+#[must_use]
+fn vault_references(config: &CccConfig) -> Vec<(String, String)> {
+    collect_fields(config, |_, value| !referenced_ids(value).is_empty())
+}
+
+/// 列出满足条件的字段 `(配置名称, 字段名)`，按名称排序
+#[must_use]
+fn collect_fields(
+    config: &CccConfig,
+    filter: impl Fn(&str, &str) -> bool,
+) -> Vec<(String, String)> {
+    let mut fields: Vec<_> = config
+        .profiles
+        .iter()
+        .flat_map(|(name, profile)| {
+            profile
+                .env
+                .vars()
+                .into_iter()
+                .filter(|(key, value)| filter(key, value))
+                .map(|(key, _)| (name.clone(), key.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    fields.sort();
+    fields
+}
@@ -2,12 +2,26 @@
 //!
 //! 包含 CCCRS 配置文件和 Claude Settings 的读写功能
 
+pub mod atomic;
+pub mod backup;
 pub mod cccrs_config;
 pub mod claude_settings;
+pub mod dir_profile;
+pub mod lock;
 pub mod paths;
+pub mod permissions;
+pub mod resolve;
+pub mod vault;
 
 pub use paths::{ensure_ccc_config_exists, validate_profile_name};
 
-pub use cccrs_config::{CccConfig, EnvConfig, Profile};
+pub use cccrs_config::{
+    API_KEY_KEY, AUTH_TOKEN_KEY, BASE_URL_KEY, BackupRetention, CccConfig, EnvConfig,
+    FailoverRecord, ManagedKeys, Profile, SkippedCandidate, is_secret_env_key, mask_secret,
+};
 
 pub use claude_settings::{ClaudeEnv, ClaudeSettings};
+
+pub use lock::{ConfigLock, LockError};
+
+pub use resolve::{expand_profile, resolve_env};
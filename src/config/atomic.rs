@@ -0,0 +1,238 @@
+//! 原子写入模块
+//!
+//! 提供崩溃安全的文件写入功能：先写入同目录下的临时文件并同步到磁盘，
+//! 再通过重命名覆盖原文件，避免写入中断时留下被截断的配置文件。目标为符号链接时写入链接指向的文件
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+#[cfg(unix)]
+use super::permissions::PRIVATE_MODE;
+
+/// 原子地将内容写入文件
+///
+/// 如果目标文件已存在，会保留其权限
+///
+/// # Errors
+///
+/// 返回临时文件创建、写入、同步或重命名错误
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    write_with_mode(path, contents, false)
+}
+
+/// 原子地将内容写入仅当前用户可读写的文件
+///
+/// 用于包含 API Key 的文件：无论 umask 和原文件权限如何，写入后权限均为 0600 (Unix only)
+///
+/// # Errors
+///
+/// 返回临时文件创建、写入、同步或重命名错误
+pub fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    write_with_mode(path, contents, true)
+}
+
+/// 原子写入，`private` 为 `true` 时设置权限为 0600，否则保留原文件权限
+fn write_with_mode(path: &Path, contents: &[u8], private: bool) -> std::io::Result<()> {
+    let target = resolve_target(path);
+    let temp_path = temp_path_for(&target);
+    let result = write_temp_and_rename(&target, &temp_path, contents, private);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// 写入临时文件并重命名为目标文件
+fn write_temp_and_rename(
+    path: &Path,
+    temp_path: &Path,
+    contents: &[u8],
+    private: bool,
+) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(PRIVATE_MODE);
+    }
+    let mut file = options.open(temp_path)?;
+    file.write_all(contents)?;
+
+    if private {
+        set_private(&file)?;
+    } else if let Ok(metadata) = std::fs::metadata(path) {
+        // 保留原文件权限
+        file.set_permissions(metadata.permissions())?;
+    }
+
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::rename(temp_path, path)?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+/// 解析实际写入的文件
+///
+/// 目标为符号链接（例如 dotfiles 仓库中的配置）时写入链接指向的文件，避免重命名时用普通文件替换链接；
+/// 链接无法解析时直接写入目标路径
+fn resolve_target(path: &Path) -> PathBuf {
+    let is_symlink =
+        std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+    if is_symlink {
+        std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// 获取目标文件对应的临时文件路径（位于同一目录）
+#[must_use]
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.tmp.{}", file_name, std::process::id()))
+}
+
+/// 将文件权限设置为 0600（临时文件可能已存在，`mode` 只在创建时生效）
+#[cfg(unix)]
+fn set_private(file: &std::fs::File) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(std::fs::Permissions::from_mode(PRIVATE_MODE))
+}
+
+/// 设置文件权限（非 Unix 平台无需处理）
+#[cfg(not(unix))]
+const fn set_private(_file: &std::fs::File) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// 同步父目录，确保重命名操作落盘 (Unix only)
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = std::fs::File::open(parent)
+    {
+        let _ = dir.sync_all();
+    }
+}
+
+/// 同步父目录（非 Unix 平台无需处理）
+#[cfg(not(unix))]
+const fn sync_parent_dir(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, Result};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic_replaces_content() -> Result<()> {
+        let temp_dir = TempDir::new().context("创建临时目录失败")?;
+        let path = temp_dir.path().join("settings.json");
+        std::fs::write(&path, "old").context("写入文件失败")?;
+
+        write_atomic(&path, b"new").context("原子写入失败")?;
+
+        let content = std::fs::read_to_string(&path).context("读取文件失败")?;
+        if content != "new" {
+            anyhow::bail!("文件内容不匹配");
+        }
+
+        // 不应遗留临时文件
+        let entries = std::fs::read_dir(temp_dir.path())
+            .context("读取目录失败")?
+            .count();
+        if entries != 1 {
+            anyhow::bail!("目录中存在多余文件");
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().context("创建临时目录失败")?;
+        let path = temp_dir.path().join("settings.json");
+        std::fs::write(&path, "old").context("写入文件失败")?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640))
+            .context("设置权限失败")?;
+
+        write_atomic(&path, b"new").context("原子写入失败")?;
+
+        let mode = std::fs::metadata(&path)
+            .context("获取文件元数据失败")?
+            .permissions()
+            .mode();
+        if mode & 0o777 != 0o640 {
+            anyhow::bail!("文件权限未保留: {:o}", mode & 0o777);
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_follows_symlink() -> Result<()> {
+        let temp_dir = TempDir::new().context("创建临时目录失败")?;
+        let dotfiles = temp_dir.path().join("dotfiles");
+        let claude = temp_dir.path().join("claude");
+        std::fs::create_dir_all(&dotfiles).context("创建目录失败")?;
+        std::fs::create_dir_all(&claude).context("创建目录失败")?;
+        let real = dotfiles.join("settings.json");
+        let link = claude.join("settings.json");
+        std::fs::write(&real, "old").context("写入文件失败")?;
+        std::os::unix::fs::symlink(&real, &link).context("创建符号链接失败")?;
+
+        write_private(&link, b"new").context("原子写入失败")?;
+
+        let metadata = std::fs::symlink_metadata(&link).context("获取文件元数据失败")?;
+        if !metadata.file_type().is_symlink() {
+            anyhow::bail!("符号链接被替换为普通文件");
+        }
+        if std::fs::read_to_string(&real).context("读取文件失败")? != "new" {
+            anyhow::bail!("链接指向的文件未更新");
+        }
+        // 临时文件创建在链接指向的目录中，且不应遗留
+        let entries = std::fs::read_dir(&claude).context("读取目录失败")?.count()
+            + std::fs::read_dir(&dotfiles)
+                .context("读取目录失败")?
+                .count();
+        if entries != 2 {
+            anyhow::bail!("目录中存在多余文件");
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private_restricts_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().context("创建临时目录失败")?;
+        let path = temp_dir.path().join("cccrs-config.json");
+        std::fs::write(&path, "old").context("写入文件失败")?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))
+            .context("设置权限失败")?;
+
+        write_private(&path, b"new").context("原子写入失败")?;
+
+        let mode = std::fs::metadata(&path)
+            .context("获取文件元数据失败")?
+            .permissions()
+            .mode();
+        if mode & 0o777 != 0o600 {
+            anyhow::bail!("文件权限应为 600: {:o}", mode & 0o777);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,396 @@
+//! 备份管理模块
+//!
+//! 管理 `~/.claude/cccrs-backups/` 下 `settings.json.backup.<时间戳>` 形式的 settings 备份文件。
+//! 旧版本直接存放在 `~/.claude/` 下的备份会在下次备份或清理备份时移动到备份目录
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDateTime, TimeDelta};
+
+use crate::error::CccError;
+
+use super::{
+    atomic::write_private,
+    cccrs_config::{BackupRetention, CccConfig},
+    paths::{get_backup_dir, get_claude_settings_path},
+};
+
+/// 备份文件名前缀
+pub const BACKUP_PREFIX: &str = "settings.json.backup.";
+
+/// 备份时间戳格式
+pub const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// 单个备份文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    /// 备份 ID（文件名中前缀之后的部分）
+    pub id: String,
+    /// 备份文件路径
+    pub path: PathBuf,
+    /// 备份创建时间
+    pub created: NaiveDateTime,
+    /// 同一秒内的备份序号（无序号后缀时为 0）
+    pub seq: u32,
+}
+
+impl Backup {
+    /// 从备份文件路径解析备份信息
+    ///
+    /// 文件名不符合 `settings.json.backup.<时间戳>[_序号]` 格式时返回 `None`
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+        let id = file_name.strip_prefix(BACKUP_PREFIX)?;
+        let timestamp = id.get(..15)?;
+        let created = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+        let seq = match id.get(15..)? {
+            "" => 0,
+            suffix => {
+                let digits = suffix.strip_prefix('_')?;
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                digits.parse().ok()?
+            }
+        };
+        Some(Self {
+            id: id.to_string(),
+            path: path.to_path_buf(),
+            created,
+            seq,
+        })
+    }
+
+    /// 备份距今的时长
+    #[must_use]
+    pub fn age(&self) -> TimeDelta {
+        Local::now().naive_local() - self.created
+    }
+}
+
+/// 获取旧版本备份所在目录（settings.json 所在目录）
+fn legacy_backup_dir() -> Result<PathBuf> {
+    let settings_path = get_claude_settings_path()?;
+    Ok(settings_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default())
+}
+
+/// 将旧版本存放在 settings.json 旁边的备份移动到备份目录，返回移动的备份数量
+///
+/// 备份目录中已存在同名文件时保留原位置的文件（列出备份时只显示备份目录中的文件）
+///
+/// # Errors
+///
+/// 返回目录创建、读取或文件移动错误
+pub fn migrate_legacy_backups() -> Result<usize> {
+    let legacy_dir = legacy_backup_dir()?;
+    if !legacy_dir.is_dir() {
+        return Ok(0);
+    }
+    let legacy: Vec<_> = std::fs::read_dir(&legacy_dir)
+        .context("读取备份目录失败")?
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| Backup::from_path(&entry.path()))
+        .collect();
+    if legacy.is_empty() {
+        return Ok(0);
+    }
+
+    let dir = get_backup_dir()?;
+    std::fs::create_dir_all(&dir).context("创建备份目录失败")?;
+    let mut moved = 0;
+    for backup in legacy {
+        let target = dir.join(format!("{}{}", BACKUP_PREFIX, backup.id));
+        if target.exists() {
+            continue;
+        }
+        std::fs::rename(&backup.path, &target)
+            .with_context(|| format!("移动备份失败: {}", backup.path.display()))?;
+        moved += 1;
+    }
+    Ok(moved)
+}
+
+/// 生成新备份文件的路径
+///
+/// 同一秒内多次备份时追加序号，避免覆盖已有备份
+///
+/// # Errors
+///
+/// 无法确定备份目录时返回错误
+pub fn new_backup_path() -> Result<PathBuf> {
+    let dir = get_backup_dir()?;
+    let timestamp = Local::now().format(TIMESTAMP_FORMAT).to_string();
+    let mut path = dir.join(format!("{}{}", BACKUP_PREFIX, timestamp));
+    let mut seq = 1;
+    while path.exists() {
+        path = dir.join(format!("{}{}_{}", BACKUP_PREFIX, timestamp, seq));
+        seq += 1;
+    }
+    Ok(path)
+}
+
+/// 备份当前的 settings.json 并返回备份路径
+///
+/// 备份文件为 `~/.claude/cccrs-backups/settings.json.backup.<时间戳>`（权限为 0600），
+/// 备份完成后会移动旧版本的备份，并按 `cccrs-config.json` 中的保留策略自动清理旧备份。
+/// `protected` 为不参与清理的备份 ID（例如正在恢复的备份）
+///
+/// # Errors
+///
+/// 返回文件读取或写入错误
+pub fn backup_settings(protected: Option<&str>) -> Result<PathBuf> {
+    let path = get_claude_settings_path()?;
+    let backup_path = new_backup_path()?;
+    if let Some(parent) = backup_path.parent() {
+        std::fs::create_dir_all(parent).context("创建备份目录失败")?;
+    }
+    // 不使用 fs::copy，避免备份继承原文件过于宽松的权限
+    let content = std::fs::read(&path).context("备份失败")?;
+    write_private(&backup_path, &content).context("备份失败")?;
+
+    // 移动旧版本的备份并按保留策略清理旧备份，失败不影响本次备份
+    let _ = migrate_legacy_backups();
+    let retention = CccConfig::load()
+        .map(|config| config.backup_retention)
+        .unwrap_or_default();
+    let _ = apply_retention(&retention, protected);
+
+    Ok(backup_path)
+}
+
+/// 列出所有备份（包括尚未移动到备份目录的旧版本备份），按创建时间从旧到新排序
+///
+/// 不修改任何文件；备份目录和旧位置中存在同名备份时只列出备份目录中的文件
+///
+/// # Errors
+///
+/// 返回目录读取错误
+pub fn list_backups() -> Result<Vec<Backup>> {
+    let mut backups: Vec<Backup> = Vec::new();
+    for dir in [get_backup_dir()?, legacy_backup_dir()?] {
+        if !dir.is_dir() {
+            continue;
+        }
+        let found: Vec<Backup> = std::fs::read_dir(&dir)
+            .context("读取备份目录失败")?
+            .filter_map(std::result::Result::ok)
+            .filter_map(|entry| Backup::from_path(&entry.path()))
+            .filter(|backup| backups.iter().all(|listed| listed.id != backup.id))
+            .collect();
+        backups.extend(found);
+    }
+    sort_backups(&mut backups);
+    Ok(backups)
+}
+
+/// 按创建时间和序号从旧到新排序
+fn sort_backups(backups: &mut [Backup]) {
+    backups.sort_by(|a, b| {
+        a.created
+            .cmp(&b.created)
+            .then_with(|| a.seq.cmp(&b.seq))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+/// 根据 ID 查找备份
+///
+/// `latest` 表示最新的备份；其他 ID 需与备份 ID 完全一致
+///
+/// # Errors
+///
+/// 返回目录读取错误或备份不存在错误
+pub fn find_backup(id: &str) -> Result<Backup> {
+    let backups = list_backups()?;
+    let found = if id == "latest" {
+        backups.into_iter().last()
+    } else {
+        backups.into_iter().find(|backup| backup.id == id)
+    };
+    found.ok_or_else(|| CccError::BackupNotFound(id.to_string()).into())
+}
+
+/// 选出需要清理的备份
+///
+/// 超出保留数量 `keep`（保留最新的 N 个）或早于 `older_than` 的备份会被选中
+#[must_use]
+pub fn select_prunable(
+    backups: &[Backup],
+    keep: Option<usize>,
+    older_than: Option<TimeDelta>,
+) -> Vec<Backup> {
+    let excess = keep.map_or(0, |keep| backups.len().saturating_sub(keep));
+    backups
+        .iter()
+        .enumerate()
+        .filter(|(index, backup)| {
+            *index < excess || older_than.is_some_and(|max_age| backup.age() > max_age)
+        })
+        .map(|(_, backup)| backup.clone())
+        .collect()
+}
+
+/// 清理备份并返回被删除的备份
+///
+/// # Errors
+///
+/// 返回目录读取或文件删除错误
+pub fn prune_backups(keep: Option<usize>, older_than: Option<TimeDelta>) -> Result<Vec<Backup>> {
+    remove_backups(select_prunable(&list_backups()?, keep, older_than))
+}
+
+/// 删除备份文件并返回被删除的备份
+fn remove_backups(prunable: Vec<Backup>) -> Result<Vec<Backup>> {
+    for backup in &prunable {
+        std::fs::remove_file(&backup.path)
+            .with_context(|| format!("删除备份失败: {}", backup.path.display()))?;
+    }
+    Ok(prunable)
+}
+
+/// 按保留策略清理备份并返回被删除的备份，ID 为 `protected` 的备份不会被清理
+///
+/// # Errors
+///
+/// 返回保留时长格式错误、目录读取或文件删除错误
+pub fn apply_retention(
+    retention: &BackupRetention,
+    protected: Option<&str>,
+) -> Result<Vec<Backup>> {
+    let keep = (retention.keep > 0).then_some(retention.keep);
+    let max_age = retention.max_age.as_deref().map(parse_age).transpose()?;
+    if keep.is_none() && max_age.is_none() {
+        return Ok(Vec::new());
+    }
+    let mut prunable = select_prunable(&list_backups()?, keep, max_age);
+    prunable.retain(|backup| Some(backup.id.as_str()) != protected);
+    remove_backups(prunable)
+}
+
+/// 解析时长字符串，例如 `30d`、`12h`、`2w`、`45m`
+///
+/// # Errors
+///
+/// 返回格式错误
+pub fn parse_age(input: &str) -> Result<TimeDelta> {
+    let input = input.trim();
+    let unit_start = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow::anyhow!("时长 '{}' 缺少单位 (可用: m, h, d, w)", input))?;
+    let (number, unit) = input.split_at(unit_start);
+    let value: i64 = number
+        .parse()
+        .with_context(|| format!("无效的时长: '{}'", input))?;
+    let delta = match unit {
+        "m" => TimeDelta::try_minutes(value),
+        "h" => TimeDelta::try_hours(value),
+        "d" => TimeDelta::try_days(value),
+        "w" => TimeDelta::try_weeks(value),
+        _ => anyhow::bail!("无效的时长单位 '{}' (可用: m, h, d, w)", unit),
+    };
+    delta.ok_or_else(|| anyhow::anyhow!("时长过大: '{}'", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup_at(id: &str) -> Result<Backup> {
+        Backup::from_path(Path::new(&format!("{}{}", BACKUP_PREFIX, id)))
+            .ok_or_else(|| anyhow::anyhow!("解析备份失败: {}", id))
+    }
+
+    #[test]
+    fn test_backup_from_path() -> Result<()> {
+        let backup = backup_at("20260102_030405_1")?;
+        if backup.id != "20260102_030405_1" {
+            anyhow::bail!("备份 ID 不匹配");
+        }
+        if backup.created.format(TIMESTAMP_FORMAT).to_string() != "20260102_030405" {
+            anyhow::bail!("备份时间不匹配");
+        }
+        if Backup::from_path(Path::new("settings.json.backup.invalid")).is_some() {
+            anyhow::bail!("无效的备份文件名不应被解析");
+        }
+        if Backup::from_path(Path::new("settings.json")).is_some() {
+            anyhow::bail!("非备份文件不应被解析");
+        }
+        for invalid in ["20260102_030405x", "20260102_030405_", "20260102_030405_1a"] {
+            if backup_at(invalid).is_ok() {
+                anyhow::bail!("序号无效的备份 '{}' 不应被解析", invalid);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup_seq_order() -> Result<()> {
+        let mut backups = vec![
+            backup_at("20260102_030405_10")?,
+            backup_at("20260102_030405_2")?,
+            backup_at("20260102_030405")?,
+        ];
+        sort_backups(&mut backups);
+        let ids: Vec<_> = backups.iter().map(|b| b.id.as_str()).collect();
+        if ids != ["20260102_030405", "20260102_030405_2", "20260102_030405_10"] {
+            anyhow::bail!("序号应按数字排序: {:?}", ids);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_prunable_by_count() -> Result<()> {
+        let backups = vec![
+            backup_at("20260101_000000")?,
+            backup_at("20260102_000000")?,
+            backup_at("20260103_000000")?,
+        ];
+        let prunable = select_prunable(&backups, Some(1), None);
+        let ids: Vec<_> = prunable.iter().map(|b| b.id.as_str()).collect();
+        if ids != ["20260101_000000", "20260102_000000"] {
+            anyhow::bail!("清理结果不匹配: {:?}", ids);
+        }
+        if !select_prunable(&backups, Some(5), None).is_empty() {
+            anyhow::bail!("未超出保留数量时不应清理");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_prunable_by_age() -> Result<()> {
+        let old = backup_at("20000101_000000")?;
+        let recent = Backup {
+            created: Local::now().naive_local(),
+            ..backup_at("20000101_000000")?
+        };
+        let prunable = select_prunable(&[old.clone(), recent], None, Some(TimeDelta::days(1)));
+        if prunable != [old] {
+            anyhow::bail!("应只清理过期的备份");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_age() -> Result<()> {
+        if parse_age("30d")? != TimeDelta::days(30) {
+            anyhow::bail!("解析天数失败");
+        }
+        if parse_age("2w")? != TimeDelta::weeks(2) {
+            anyhow::bail!("解析周数失败");
+        }
+        if parse_age("12h")? != TimeDelta::hours(12) {
+            anyhow::bail!("解析小时失败");
+        }
+        for invalid in ["30", "d", "10y", ""] {
+            if parse_age(invalid).is_ok() {
+                anyhow::bail!("无效时长 '{}' 不应被接受", invalid);
+            }
+        }
+        Ok(())
+    }
+}
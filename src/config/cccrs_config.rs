@@ -2,12 +2,20 @@
 //!
 //! 定义 CCCRS 配置数据结构并提供读写功能
 
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
-use super::paths::{ensure_ccc_config_exists, get_ccc_config_path};
+use crate::error::CccError;
+
+use super::{
+    atomic::write_private,
+    paths::{ensure_ccc_config_exists, get_ccc_config_path},
+};
 
 /// CCCRS 主配置文件结构
 ///
@@ -19,19 +27,157 @@ pub struct CccConfig {
     /// 当前活跃配置名称
     #[serde(skip_serializing_if = "Option::is_none")]
     pub current: Option<String>,
+    /// 由 cccrs 写入 settings.json 的字段
+    #[serde(default, skip_serializing_if = "ManagedKeys::is_empty")]
+    pub managed: ManagedKeys,
+    /// 由 cccrs 写入项目级 settings 文件的字段，按 settings 文件路径区分
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub project_managed: BTreeMap<String, ManagedKeys>,
+    /// 备份保留策略
+    #[serde(default, skip_serializing_if = "BackupRetention::is_default")]
+    pub backup_retention: BackupRetention,
+    /// 本地路由代理转发请求的目标配置档案，未设置时使用当前活跃配置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_target: Option<String>,
+    /// 最近一次 `use --auto` 的选择结果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failover: Option<FailoverRecord>,
+}
+
+/// `use --auto` 自动故障切换的选择记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailoverRecord {
+    /// 指定的配置档案（候选列表的第一项）
+    pub group: String,
+    /// 最终选择的配置档案
+    pub profile: String,
+    /// 选择时间（RFC 3339）
+    pub checked_at: String,
+    /// 排在所选配置之前、因检查失败被跳过的候选
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedCandidate>,
+}
+
+/// 被跳过的故障切换候选
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedCandidate {
+    /// 配置档案名称
+    pub profile: String,
+    /// 跳过原因
+    pub reason: String,
+}
+
+/// 默认保留的备份数量
+pub const DEFAULT_BACKUP_KEEP: usize = 20;
+
+/// settings.json 备份保留策略
+///
+/// 每次备份后自动清理超出数量或超过时长的旧备份
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupRetention {
+    /// 最多保留的备份数量，`0` 表示不限制
+    #[serde(default = "default_backup_keep")]
+    pub keep: usize,
+    /// 备份最长保留时长（例如 `30d`），未设置表示不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+}
+
+/// 默认保留的备份数量（供 serde 使用）
+const fn default_backup_keep() -> usize {
+    DEFAULT_BACKUP_KEEP
+}
+
+impl Default for BackupRetention {
+    fn default() -> Self {
+        Self {
+            keep: DEFAULT_BACKUP_KEEP,
+            max_age: None,
+        }
+    }
+}
+
+impl BackupRetention {
+    /// 是否为默认策略
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 由 cccrs 管理的 settings.json 字段记录
+///
+/// 用于在切换配置时区分 cccrs 写入的字段和用户自行添加的字段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct ManagedKeys {
+    /// 是否写入过 `apiKeyHelper`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub api_key_helper: bool,
+    /// 写入过的 `env` 变量名
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub env: BTreeSet<String>,
 }
 
 /// 单个配置档案
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Profile {
+    /// 继承的父配置档案名称，未定义的字段从父配置继承
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
     /// API Key Helper 命令
     #[serde(skip_serializing_if = "Option::is_none")]
     pub api_key_helper: Option<String>,
     /// 环境变量配置
+    #[serde(default)]
     pub env: EnvConfig,
+    /// 自身不可用时依次尝试的备用配置档案（`use --auto`），不会被继承
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+}
+
+/// `ANTHROPIC_BASE_URL` 环境变量名
+pub const BASE_URL_KEY: &str = "ANTHROPIC_BASE_URL";
+
+/// `ANTHROPIC_AUTH_TOKEN` 环境变量名
+pub const AUTH_TOKEN_KEY: &str = "ANTHROPIC_AUTH_TOKEN";
+
+/// `ANTHROPIC_API_KEY` 环境变量名
+///
+/// 在不修改 settings.json 的情况下使用配置档案时，`apiKeyHelper` 的输出通过该变量传递
+pub const API_KEY_KEY: &str = "ANTHROPIC_API_KEY";
+
+/// 判断环境变量名是否可能包含敏感信息（如 API Key、Token）
+#[must_use]
+pub fn is_secret_env_key(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase();
+    ["KEY", "TOKEN", "SECRET", "PASSWORD", "HEADERS"]
+        .iter()
+        .any(|word| upper.contains(word))
+}
+
+/// 隐藏敏感值，只显示少量字符
+///
+/// 不少于 24 个字符时显示首尾各 4 个字符（例如 `sk-ant-REDACTED` 显示为 `sk-a****ghij`）；
+/// 12 到 23 个字符时只显示前 4 个字符（例如 `sk-abcdefghijklmnop` 显示为 `sk-a****`）；
+/// 更短的值完全隐藏
+#[must_use]
+pub fn mask_secret(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 12 {
+        return "****".to_string();
+    }
+    let head: String = chars.iter().take(4).collect();
+    if chars.len() < 24 {
+        return format!("{}****", head);
+    }
+    let tail: String = chars.iter().skip(chars.len() - 4).collect();
+    format!("{}****{}", head, tail)
 }
 
 /// 环境变量配置
+///
+/// `ANTHROPIC_BASE_URL` 和 `ANTHROPIC_AUTH_TOKEN` 作为常用字段单独存放，
+/// 其余环境变量（如 `ANTHROPIC_MODEL`、`API_TIMEOUT_MS`）存放在 `extra` 中
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct EnvConfig {
     /// Anthropic API Base URL
@@ -43,6 +189,9 @@ pub struct EnvConfig {
         rename = "ANTHROPIC_AUTH_TOKEN"
     )]
     pub anthropic_api_key: Option<String>,
+    /// 其他环境变量
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, String>,
 }
 
 impl CccConfig {
@@ -52,24 +201,35 @@ impl CccConfig {
     ///
     /// # Errors
     ///
-    /// 返回文件读取错误或 JSON 解析错误
+    /// 返回文件读取错误、JSON 解析错误或配置档案中包含无效环境变量名的错误
     pub fn load() -> Result<Self> {
         ensure_ccc_config_exists().context("创建配置文件失败")?;
-        let path = get_ccc_config_path();
+        let path = get_ccc_config_path()?;
         let content = std::fs::read_to_string(&path).context("读取配置文件失败")?;
-        serde_json::from_str(&content).context("解析配置文件失败")
+        let config: Self = serde_json::from_str(&content).context("解析配置文件失败")?;
+        // 环境变量名会被写入 shell 语句，必须在使用前校验
+        for (name, profile) in &config.profiles {
+            if let Some(key) = profile.env.invalid_key() {
+                return Err(CccError::InvalidEnvKey(key.to_string())).with_context(|| {
+                    format!("配置档案 '{}' 无效，请修改 {}", name, path.display())
+                });
+            }
+        }
+        Ok(config)
     }
 
     /// 保存配置到文件
     ///
+    /// 使用原子写入，写入中断时不会破坏原文件；配置中包含 API Key，文件权限为 0600
+    ///
     /// # Errors
     ///
     /// 返回文件写入错误
     pub fn save(&self) -> Result<()> {
         ensure_ccc_config_exists().context("创建配置文件失败")?;
-        let path = get_ccc_config_path();
+        let path = get_ccc_config_path()?;
         let content = serde_json::to_string_pretty(self).context("序列化配置失败")?;
-        std::fs::write(&path, content).context("写入配置文件失败")
+        write_private(&path, content.as_bytes()).context("写入配置文件失败")
     }
 
     /// 检查配置档案是否存在
@@ -89,6 +249,219 @@ impl CccConfig {
         self.profiles.insert(name, profile);
     }
 
+    /// 获取继承了指定配置档案的配置档案名称（按名称排序）
+    #[must_use]
+    pub fn children_of(&self, name: &str) -> Vec<&str> {
+        let mut children: Vec<&str> = self
+            .profiles
+            .iter()
+            .filter(|(_, profile)| profile.extends.as_deref() == Some(name))
+            .map(|(child, _)| child.as_str())
+            .collect();
+        children.sort_unstable();
+        children
+    }
+
+    /// 获取配置档案的继承链，从自身开始到最顶层的父配置
+    ///
+    /// # Errors
+    ///
+    /// 返回配置档案不存在或继承存在循环的错误
+    pub fn extends_chain<'a>(&'a self, name: &'a str) -> Result<Vec<&'a str>> {
+        let mut chain: Vec<&str> = Vec::new();
+        let mut current = name;
+        loop {
+            if chain.contains(&current) {
+                chain.push(current);
+                return Err(CccError::ExtendsCycle(chain.join(" -> ")).into());
+            }
+            let profile = self.get_profile(current).ok_or_else(|| {
+                let err = anyhow::Error::new(CccError::ProfileNotFound(current.to_string()));
+                match chain.last() {
+                    Some(child) => err.context(format!("配置档案 '{}' 继承的配置不存在", child)),
+                    None => err,
+                }
+            })?;
+            chain.push(current);
+            match profile.extends.as_deref() {
+                Some(parent) => current = parent,
+                None => return Ok(chain),
+            }
+        }
+    }
+
+    /// 解析配置档案的继承关系，返回合并后的配置档案
+    ///
+    /// 子配置中定义的字段覆盖父配置中的同名字段，结果中不再包含 `extends`
+    ///
+    /// # Errors
+    ///
+    /// 返回配置档案不存在或继承存在循环的错误
+    pub fn resolve_profile(&self, name: &str) -> Result<Profile> {
+        let mut resolved = Profile::new(None, EnvConfig::default());
+        for profile in self
+            .extends_chain(name)?
+            .into_iter()
+            .rev()
+            .filter_map(|name| self.get_profile(name))
+        {
+            if let Some(helper) = &profile.api_key_helper {
+                resolved.api_key_helper = Some(helper.clone());
+            }
+            for (key, value) in profile.env.vars() {
+                resolved.env.set(key, value.to_string());
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// 获取 `use --auto` 的候选配置档案：自身及其备用配置（按顺序，去除重复项）
+    ///
+    /// # Errors
+    ///
+    /// 返回配置档案或备用配置档案不存在的错误
+    pub fn fallback_candidates<'a>(&'a self, name: &'a str) -> Result<Vec<&'a str>> {
+        let profile = self
+            .get_profile(name)
+            .ok_or_else(|| CccError::ProfileNotFound(name.to_string()))?;
+        let mut candidates = vec![name];
+        for fallback in &profile.fallback {
+            if !self.has_profile(fallback) {
+                return Err(
+                    anyhow::Error::new(CccError::ProfileNotFound(fallback.clone()))
+                        .context(format!("配置档案 '{}' 的备用配置不存在", name)),
+                );
+            }
+            if !candidates.contains(&fallback.as_str()) {
+                candidates.push(fallback);
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// 获取将指定配置档案列为备用配置的配置档案名称（按名称排序）
+    #[must_use]
+    pub fn fallback_referrers(&self, name: &str) -> Vec<&str> {
+        let mut referrers: Vec<&str> = self
+            .profiles
+            .iter()
+            .filter(|(_, profile)| profile.fallback.iter().any(|fallback| fallback == name))
+            .map(|(referrer, _)| referrer.as_str())
+            .collect();
+        referrers.sort_unstable();
+        referrers
+    }
+
+    /// 重命名配置档案
+    ///
+    /// 覆盖已存在的 `new`；重命名当前活跃配置时同步更新记录，
+    /// 被覆盖的配置为当前活跃配置或代理目标时清除对应记录，继承 `old` 的配置档案改为继承 `new`，
+    /// 备用配置列表、代理目标和故障切换记录中的 `old` 改为 `new`。
+    /// `old` 不存在时返回 `false`
+    pub fn rename_profile(&mut self, old: &str, new: &str) -> bool {
+        let Some(profile) = self.profiles.remove(old) else {
+            return false;
+        };
+        self.profiles.insert(new.to_string(), profile);
+        for other in self.profiles.values_mut() {
+            if other.extends.as_deref() == Some(old) {
+                other.extends = Some(new.to_string());
+            }
+            for fallback in &mut other.fallback {
+                if fallback == old {
+                    *fallback = new.to_string();
+                }
+            }
+        }
+        if self.current.as_deref() == Some(old) {
+            self.current = Some(new.to_string());
+        } else if self.current.as_deref() == Some(new) {
+            self.current = None;
+        }
+        if self.proxy_target.as_deref() == Some(old) {
+            self.proxy_target = Some(new.to_string());
+        } else if self.proxy_target.as_deref() == Some(new) {
+            self.proxy_target = None;
+        }
+        if let Some(record) = &mut self.failover {
+            let names = [&mut record.group, &mut record.profile].into_iter().chain(
+                record
+                    .skipped
+                    .iter_mut()
+                    .map(|skipped| &mut skipped.profile),
+            );
+            for name in names {
+                if name == old {
+                    *name = new.to_string();
+                }
+            }
+        }
+        true
+    }
+
+    /// 复制配置档案
+    ///
+    /// 覆盖已存在的 `dst`；被覆盖的配置为当前活跃配置或代理目标时清除对应记录。
+    /// `src` 不存在时返回 `false`
+    pub fn copy_profile(&mut self, src: &str, dst: &str) -> bool {
+        let Some(profile) = self.profiles.get(src).cloned() else {
+            return false;
+        };
+        self.profiles.insert(dst.to_string(), profile);
+        if src != dst {
+            if self.current.as_deref() == Some(dst) {
+                self.current = None;
+            }
+            if self.proxy_target.as_deref() == Some(dst) {
+                self.proxy_target = None;
+            }
+        }
+        true
+    }
+
+    /// 获取指定 settings 文件中由 cccrs 写入的字段
+    ///
+    /// `project_settings` 为 `None` 表示用户级 settings
+    #[must_use]
+    pub fn managed_keys(&self, project_settings: Option<&Path>) -> ManagedKeys {
+        project_settings.map_or_else(
+            || self.managed.clone(),
+            |path| {
+                self.project_managed
+                    .get(path.to_string_lossy().as_ref())
+                    .cloned()
+                    .unwrap_or_default()
+            },
+        )
+    }
+
+    /// 记录指定 settings 文件中由 cccrs 写入的字段
+    ///
+    /// `project_settings` 为 `None` 表示用户级 settings
+    pub fn set_managed_keys(&mut self, project_settings: Option<&Path>, keys: ManagedKeys) {
+        match project_settings {
+            None => self.managed = keys,
+            Some(path) => {
+                let key = path.to_string_lossy().into_owned();
+                if keys.is_empty() {
+                    self.project_managed.remove(&key);
+                } else {
+                    self.project_managed.insert(key, keys);
+                }
+            }
+        }
+    }
+
+    /// 获取所有配置档案会设置的环境变量名（不执行 `apiKeyHelper`）
+    #[must_use]
+    pub fn all_env_keys(&self) -> BTreeSet<String> {
+        self.profiles
+            .keys()
+            .filter_map(|name| self.resolve_profile(name).ok())
+            .flat_map(|profile| profile.env_keys())
+            .collect()
+    }
+
     /// 删除配置档案
     ///
     /// 返回被删除的配置档案（如果存在）
@@ -103,10 +476,59 @@ impl Profile {
     #[must_use]
     pub const fn new(api_key_helper: Option<String>, env: EnvConfig) -> Self {
         Self {
+            extends: None,
             api_key_helper,
             env,
+            fallback: Vec::new(),
         }
     }
+
+    /// 获取配置档案会设置的环境变量名（不执行 `apiKeyHelper`）
+    ///
+    /// 配置了 `apiKeyHelper` 时包括 `ANTHROPIC_API_KEY`
+    #[must_use]
+    pub fn env_keys(&self) -> BTreeSet<String> {
+        let mut keys: BTreeSet<String> = self.env.vars().into_keys().map(str::to_string).collect();
+        if self.api_key_helper.is_some() {
+            keys.insert(API_KEY_KEY.to_string());
+        }
+        keys
+    }
+}
+
+impl ManagedKeys {
+    /// 获取配置档案会写入 settings.json 的字段
+    #[must_use]
+    pub fn from_profile(profile: &Profile) -> Self {
+        Self {
+            api_key_helper: profile.api_key_helper.is_some(),
+            env: profile.env.vars().into_keys().map(str::to_string).collect(),
+        }
+    }
+
+    /// 合并两组字段记录
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            api_key_helper: self.api_key_helper || other.api_key_helper,
+            env: self.env.union(&other.env).cloned().collect(),
+        }
+    }
+
+    /// 获取存在于 `self` 但不存在于 `other` 中的字段
+    #[must_use]
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            api_key_helper: self.api_key_helper && !other.api_key_helper,
+            env: self.env.difference(&other.env).cloned().collect(),
+        }
+    }
+
+    /// 是否没有任何字段
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        !self.api_key_helper && self.env.is_empty()
+    }
 }
 
 impl EnvConfig {
@@ -119,8 +541,96 @@ impl EnvConfig {
         Self {
             anthropic_base_url,
             anthropic_api_key,
+            extra: BTreeMap::new(),
         }
     }
+
+    /// 获取指定环境变量的值
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match key {
+            BASE_URL_KEY => self.anthropic_base_url.as_deref(),
+            AUTH_TOKEN_KEY => self.anthropic_api_key.as_deref(),
+            _ => self.extra.get(key).map(String::as_str),
+        }
+    }
+
+    /// 设置环境变量
+    pub fn set(&mut self, key: &str, value: String) {
+        match key {
+            BASE_URL_KEY => self.anthropic_base_url = Some(value),
+            AUTH_TOKEN_KEY => self.anthropic_api_key = Some(value),
+            _ => {
+                self.extra.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    /// 删除环境变量
+    ///
+    /// 返回被删除的值（如果存在）
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        match key {
+            BASE_URL_KEY => self.anthropic_base_url.take(),
+            AUTH_TOKEN_KEY => self.anthropic_api_key.take(),
+            _ => self.extra.remove(key),
+        }
+    }
+
+    /// 按名称顺序遍历所有已设置的环境变量（包括常用字段）
+    #[must_use]
+    pub fn vars(&self) -> BTreeMap<&str, &str> {
+        let mut vars: BTreeMap<&str, &str> = self
+            .extra
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        if let Some(url) = &self.anthropic_base_url {
+            vars.insert(BASE_URL_KEY, url);
+        }
+        if let Some(key) = &self.anthropic_api_key {
+            vars.insert(AUTH_TOKEN_KEY, key);
+        }
+        vars
+    }
+
+    /// 解析 `KEY=VALUE` 形式的环境变量赋值
+    ///
+    /// 变量名需满足 [`EnvConfig::is_valid_key`]
+    #[must_use]
+    pub fn parse_assignment(input: &str) -> Option<(&str, &str)> {
+        let (key, value) = input.split_once('=')?;
+        let key = key.trim();
+        Self::is_valid_key(key).then_some((key, value))
+    }
+
+    /// 检查环境变量名是否有效
+    ///
+    /// 变量名只能包含字母、数字和下划线，且不能以数字开头
+    #[must_use]
+    pub fn is_valid_key(key: &str) -> bool {
+        key.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// 获取第一个无效的环境变量名（见 [`EnvConfig::is_valid_key`]）
+    #[must_use]
+    pub fn invalid_key(&self) -> Option<&str> {
+        self.extra
+            .keys()
+            .map(String::as_str)
+            .find(|key| !Self::is_valid_key(key))
+    }
+
+    /// 是否未设置任何环境变量
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.anthropic_base_url.is_none()
+            && self.anthropic_api_key.is_none()
+            && self.extra.is_empty()
+    }
 }
 
 #[cfg(test)]
@@ -196,4 +706,275 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_rename_and_copy_profile() -> Result<()> {
+        let (_temp_dir, mut config) = setup_temp_config()?;
+        let profile = Profile::new(None, EnvConfig::new(Some("https://a".to_string()), None));
+        config.insert_profile("old".to_string(), profile.clone());
+        config.current = Some("old".to_string());
+        config.failover = Some(FailoverRecord {
+            group: "old".to_string(),
+            profile: "other".to_string(),
+            checked_at: "2026-01-01T00:00:00+08:00".to_string(),
+            skipped: vec![SkippedCandidate {
+                profile: "old".to_string(),
+                reason: "HTTP 529".to_string(),
+            }],
+        });
+
+        if !config.rename_profile("old", "new") {
+            anyhow::bail!("重命名失败");
+        }
+        if config.has_profile("old") || config.get_profile("new") != Some(&profile) {
+            anyhow::bail!("重命名结果不匹配");
+        }
+        if config.current.as_deref() != Some("new") {
+            anyhow::bail!("当前配置记录未更新");
+        }
+        let record = config.failover.as_ref().context("故障切换记录不应被清除")?;
+        if record.group != "new"
+            || record.profile != "other"
+            || record
+                .skipped
+                .first()
+                .map(|skipped| skipped.profile.as_str())
+                != Some("new")
+        {
+            anyhow::bail!("故障切换记录未更新: {:?}", record);
+        }
+
+        if !config.copy_profile("new", "copied") || config.get_profile("copied") != Some(&profile) {
+            anyhow::bail!("复制结果不匹配");
+        }
+        if config.current.as_deref() != Some("new") {
+            anyhow::bail!("复制不应修改当前配置记录");
+        }
+
+        // 覆盖当前活跃配置或代理目标时清除记录
+        config.proxy_target = Some("copied".to_string());
+        if !config.copy_profile("new", "copied") || config.proxy_target.is_some() {
+            anyhow::bail!("覆盖代理目标后应清除记录");
+        }
+        if !config.rename_profile("copied", "new") || config.current.is_some() {
+            anyhow::bail!("覆盖当前配置后应清除记录");
+        }
+        if config.rename_profile("missing", "other") || config.copy_profile("missing", "other") {
+            anyhow::bail!("不存在的配置档案应返回 false");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_profile_extends() -> Result<()> {
+        let (_temp_dir, mut config) = setup_temp_config()?;
+        let mut base_env = EnvConfig::new(Some("https://api.base.com".to_string()), None);
+        base_env.set("ANTHROPIC_MODEL", "base-model".to_string());
+        config.insert_profile(
+            "base".to_string(),
+            Profile::new(Some("echo base".to_string()), base_env),
+        );
+        let mut child = Profile::new(None, EnvConfig::new(None, Some("sk-child".to_string())));
+        child.env.set("ANTHROPIC_MODEL", "child-model".to_string());
+        child.extends = Some("base".to_string());
+        config.insert_profile("child".to_string(), child);
+
+        let resolved = config.resolve_profile("child")?;
+        if resolved.env.get(BASE_URL_KEY) != Some("https://api.base.com")
+            || resolved.env.get(AUTH_TOKEN_KEY) != Some("sk-child")
+            || resolved.env.get("ANTHROPIC_MODEL") != Some("child-model")
+            || resolved.api_key_helper.as_deref() != Some("echo base")
+            || resolved.extends.is_some()
+        {
+            anyhow::bail!("继承解析结果不匹配: {:?}", resolved);
+        }
+        if config.extends_chain("child")? != ["child", "base"] {
+            anyhow::bail!("继承链不匹配");
+        }
+
+        // 循环继承
+        if let Some(base) = config.profiles.get_mut("base") {
+            base.extends = Some("child".to_string());
+        }
+        let err = config
+            .resolve_profile("child")
+            .err()
+            .context("循环继承应返回错误")?;
+        if !err.to_string().contains("child -> base -> child") {
+            anyhow::bail!("循环错误信息不匹配: {}", err);
+        }
+
+        // 重命名父配置时更新子配置的继承
+        if let Some(base) = config.profiles.get_mut("base") {
+            base.extends = None;
+        }
+        let _ = config.rename_profile("base", "root");
+        if config
+            .get_profile("child")
+            .and_then(|p| p.extends.as_deref())
+            != Some("root")
+        {
+            anyhow::bail!("重命名后子配置的继承未更新");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_candidates() -> Result<()> {
+        let (_temp_dir, mut config) = setup_temp_config()?;
+        for name in ["primary", "backup", "spare"] {
+            let env = EnvConfig::new(Some(format!("https://{}", name)), None);
+            config.insert_profile(name.to_string(), Profile::new(None, env));
+        }
+        if let Some(primary) = config.profiles.get_mut("primary") {
+            primary.fallback = vec![
+                "backup".to_string(),
+                "primary".to_string(),
+                "spare".to_string(),
+            ];
+        }
+
+        if config.fallback_candidates("primary")? != ["primary", "backup", "spare"] {
+            anyhow::bail!("候选列表不匹配");
+        }
+        if config.fallback_candidates("spare")? != ["spare"] {
+            anyhow::bail!("没有备用配置时候选列表应只包含自身");
+        }
+        if config.fallback_referrers("backup") != ["primary"] {
+            anyhow::bail!("引用备用配置的配置档案不匹配");
+        }
+
+        // 重命名备用配置时更新引用
+        let _ = config.rename_profile("backup", "secondary");
+        if config.fallback_candidates("primary")? != ["primary", "secondary", "spare"] {
+            anyhow::bail!("重命名后备用配置未更新");
+        }
+
+        let _ = config.remove_profile("spare");
+        let err = config
+            .fallback_candidates("primary")
+            .err()
+            .context("备用配置不存在时应返回错误")?;
+        if crate::error::error_code(&err) != "profile_not_found" {
+            anyhow::bail!("错误码不匹配: {:#}", err);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_config_extra_vars_roundtrip() -> Result<()> {
+        let json = r#"{
+            "ANTHROPIC_BASE_URL": "https://api.test.com",
+            "ANTHROPIC_AUTH_TOKEN": "sk-test",
+            "ANTHROPIC_MODEL": "kimi-k2",
+            "API_TIMEOUT_MS": "600000"
+        }"#;
+        let env: EnvConfig = serde_json::from_str(json).context("解析 env 失败")?;
+        if env.anthropic_base_url.as_deref() != Some("https://api.test.com") {
+            anyhow::bail!("Base URL 不匹配");
+        }
+        if env.extra.len() != 2 || env.get("ANTHROPIC_MODEL") != Some("kimi-k2") {
+            anyhow::bail!("额外环境变量不匹配");
+        }
+        if env.vars().len() != 4 {
+            anyhow::bail!("环境变量数量不匹配");
+        }
+
+        let serialized = serde_json::to_value(&env).context("序列化 env 失败")?;
+        let reparsed: EnvConfig = serde_json::from_value(serialized).context("解析 env 失败")?;
+        if reparsed != env {
+            anyhow::bail!("往返序列化结果不匹配");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_mask_secret() -> Result<()> {
+        let masked = mask_secret("sk-ant-REDACTED");
+        if masked != "sk-a****ghij" {
+            anyhow::bail!("隐藏结果不匹配: {}", masked);
+        }
+        // 较短的值最多显示 4 个字符
+        for len in 12..24 {
+            let secret: String = "sk-abcdefghijklmnopqrstuvwxyz".chars().take(len).collect();
+            let partial = mask_secret(&secret);
+            if partial != "sk-a****" {
+                anyhow::bail!("{} 个字符的值隐藏结果不匹配: {}", len, partial);
+            }
+        }
+        for short in ["", "sk-1", "sk-12345", "sk-12345678"] {
+            if mask_secret(short) != "****" {
+                anyhow::bail!("短值应完全隐藏: {}", short);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_config_set_and_remove() -> Result<()> {
+        let mut env = EnvConfig::default();
+        env.set(AUTH_TOKEN_KEY, "sk-test".to_string());
+        env.set("ANTHROPIC_MODEL", "kimi-k2".to_string());
+        if env.anthropic_api_key.as_deref() != Some("sk-test")
+            || !env.extra.contains_key("ANTHROPIC_MODEL")
+        {
+            anyhow::bail!("设置环境变量失败");
+        }
+        if env.remove(AUTH_TOKEN_KEY).as_deref() != Some("sk-test") {
+            anyhow::bail!("删除常用字段失败");
+        }
+        if env.remove("ANTHROPIC_MODEL").is_none() || !env.is_empty() {
+            anyhow::bail!("删除额外环境变量失败");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_managed_keys_difference() -> Result<()> {
+        let mut old_env = EnvConfig::new(
+            Some("https://api.old.com".to_string()),
+            Some("sk-old".to_string()),
+        );
+        old_env.set("ANTHROPIC_MODEL", "old-model".to_string());
+        let old = ManagedKeys::from_profile(&Profile::new(Some("echo old".to_string()), old_env));
+        let new = ManagedKeys::from_profile(&Profile::new(
+            None,
+            EnvConfig::new(
+                Some("https://api.new.com".to_string()),
+                Some("sk-new".to_string()),
+            ),
+        ));
+
+        let stale = old.difference(&new);
+        if !stale.api_key_helper {
+            anyhow::bail!("apiKeyHelper 应被视为过期字段");
+        }
+        if stale.env.iter().map(String::as_str).collect::<Vec<_>>() != ["ANTHROPIC_MODEL"] {
+            anyhow::bail!("过期环境变量不匹配");
+        }
+        if old.union(&new) != old {
+            anyhow::bail!("合并结果不匹配");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_config_parse_assignment() -> Result<()> {
+        if EnvConfig::parse_assignment("API_TIMEOUT_MS=600000")
+            != Some(("API_TIMEOUT_MS", "600000"))
+        {
+            anyhow::bail!("解析赋值失败");
+        }
+        if EnvConfig::parse_assignment("ANTHROPIC_CUSTOM_HEADERS=a=b")
+            != Some(("ANTHROPIC_CUSTOM_HEADERS", "a=b"))
+        {
+            anyhow::bail!("值中的等号应保留");
+        }
+        for invalid in ["NO_EQUALS", "=value", "1ABC=x", "BAD-KEY=x"] {
+            if EnvConfig::parse_assignment(invalid).is_some() {
+                anyhow::bail!("无效赋值 '{}' 不应被接受", invalid);
+            }
+        }
+        Ok(())
+    }
 }
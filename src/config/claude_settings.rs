@@ -2,11 +2,18 @@
 //!
 //! 定义 Claude settings.json 相关结构并提供读写功能
 
+use std::{collections::BTreeMap, path::Path};
+
 use anyhow::{Context, Result};
 
 use serde::{Deserialize, Serialize};
 
-use super::paths::get_claude_settings_path;
+use super::{
+    atomic::{write_atomic, write_private},
+    cccrs_config::{AUTH_TOKEN_KEY, BASE_URL_KEY, EnvConfig, ManagedKeys, Profile},
+    paths::{SettingsScope, get_claude_settings_path},
+    resolve::has_placeholder,
+};
 
 /// Claude settings.json 中需要管理的字段
 ///
@@ -14,7 +21,7 @@ use super::paths::get_claude_settings_path;
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ClaudeSettings {
     /// API Key Helper 命令
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "apiKeyHelper", skip_serializing_if = "Option::is_none")]
     pub api_key_helper: Option<String>,
     /// 环境变量配置
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -33,6 +40,68 @@ pub struct ClaudeEnv {
         skip_serializing_if = "Option::is_none"
     )]
     pub anthropic_api_key: Option<String>,
+    /// 其他环境变量
+    ///
+    /// settings.json 中的值可能不是字符串（例如数字），因此保留原始 JSON 值
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl ClaudeEnv {
+    /// 设置环境变量
+    pub fn set(&mut self, key: &str, value: String) {
+        match key {
+            BASE_URL_KEY => self.anthropic_base_url = Some(value),
+            AUTH_TOKEN_KEY => self.anthropic_api_key = Some(value),
+            _ => {
+                self.extra.insert(key.to_string(), serde_json::json!(value));
+            }
+        }
+    }
+
+    /// 按名称顺序获取所有环境变量，非字符串值会被转换为字符串
+    #[must_use]
+    pub fn vars(&self) -> BTreeMap<String, String> {
+        let mut vars: BTreeMap<String, String> = self
+            .extra
+            .iter()
+            .map(|(k, v)| {
+                let value = v.as_str().map_or_else(|| v.to_string(), str::to_string);
+                (k.clone(), value)
+            })
+            .collect();
+        if let Some(url) = &self.anthropic_base_url {
+            vars.insert(BASE_URL_KEY.to_string(), url.clone());
+        }
+        if let Some(key) = &self.anthropic_api_key {
+            vars.insert(AUTH_TOKEN_KEY.to_string(), key.clone());
+        }
+        vars
+    }
+
+    /// 转换为配置档案中的环境变量配置
+    #[must_use]
+    pub fn to_env_config(&self) -> EnvConfig {
+        let mut env = EnvConfig::default();
+        for (key, value) in self.vars() {
+            env.set(&key, value);
+        }
+        env
+    }
+}
+
+impl From<&EnvConfig> for ClaudeEnv {
+    fn from(env: &EnvConfig) -> Self {
+        Self {
+            anthropic_base_url: env.anthropic_base_url.clone(),
+            anthropic_api_key: env.anthropic_api_key.clone(),
+            extra: env
+                .extra
+                .iter()
+                .map(|(k, v)| (k.clone(), serde_json::json!(v)))
+                .collect(),
+        }
+    }
 }
 
 impl ClaudeSettings {
@@ -42,31 +111,105 @@ impl ClaudeSettings {
     ///
     /// 返回文件读取错误或 JSON 解析错误
     pub fn load() -> Result<Self> {
-        let path = get_claude_settings_path();
-        let content = std::fs::read_to_string(&path).context("读取 settings 失败")?;
+        Self::load_from(&get_claude_settings_path()?)
+    }
+
+    /// 从指定的 settings 文件加载配置
+    ///
+    /// # Errors
+    ///
+    /// 返回文件读取错误或 JSON 解析错误
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).context("读取 settings 失败")?;
         serde_json::from_str(&content).context("解析 settings 失败")
     }
 
+    /// 获取配置档案中与当前 settings 不一致的字段
+    ///
+    /// 返回 `apiKeyHelper` 或环境变量名，全部一致时返回空列表；包含占位符的字段不参与比较
+    #[must_use]
+    pub fn mismatched_fields(&self, profile: &Profile) -> Vec<String> {
+        let settings_vars = self.env.as_ref().map(ClaudeEnv::vars).unwrap_or_default();
+        let helper_mismatch =
+            profile.api_key_helper.is_some() && profile.api_key_helper != self.api_key_helper;
+        helper_mismatch
+            .then(|| "apiKeyHelper".to_string())
+            .into_iter()
+            .chain(
+                profile
+                    .env
+                    .vars()
+                    .into_iter()
+                    .filter(|(key, value)| {
+                        !has_placeholder(value)
+                            && settings_vars.get(*key).map(String::as_str) != Some(*value)
+                    })
+                    .map(|(key, _)| key.to_string()),
+            )
+            .collect()
+    }
+
     /// 保存配置到文件（保留其他字段）
     ///
-    /// 只更新 `apiKeyHelper` 和 `env.ANTHROPIC_*` 字段，其他字段保持不变
+    /// 只更新 `apiKeyHelper` 和 `env` 中由配置档案提供的变量，其他字段保持不变
     ///
     /// # Errors
     ///
     /// 返回文件写入错误
     pub fn save(&self) -> Result<()> {
-        let path = get_claude_settings_path();
+        self.save_clean(&ManagedKeys::default())
+    }
+
+    /// 保存配置到文件，并移除 `stale` 中记录的过期字段
+    ///
+    /// 先删除 `stale` 中的字段，再写入当前配置的字段，未记录的字段保持不变。
+    /// 使用原子写入，写入中断时不会留下被截断的 settings.json
+    ///
+    /// # Errors
+    ///
+    /// 返回文件写入错误
+    pub fn save_clean(&self, stale: &ManagedKeys) -> Result<()> {
+        self.save_clean_to(SettingsScope::User, stale)
+    }
+
+    /// 保存配置到指定作用范围的 settings 文件，并移除 `stale` 中记录的过期字段
+    ///
+    /// 文件或其所在目录不存在时会自动创建。用户级和项目本地 settings 中包含 API Key，文件权限为 0600；
+    /// 项目级 settings.json 通常会提交到版本库，保留原有权限
+    ///
+    /// # Errors
+    ///
+    /// 返回路径获取或文件写入错误
+    pub fn save_clean_to(&self, scope: SettingsScope, stale: &ManagedKeys) -> Result<()> {
+        let settings_path = scope.settings_path().context("获取 settings 路径失败")?;
+        let path = settings_path.as_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("创建 settings 目录失败")?;
+        }
 
         // 读取现有文件，保留其他字段
         let existing: serde_json::Value = if path.exists() {
-            let content = std::fs::read_to_string(&path).context("读取 settings 失败")?;
+            let content = std::fs::read_to_string(path).context("读取 settings 失败")?;
             serde_json::from_str(&content).context("解析现有 settings 失败")?
         } else {
             serde_json::json!({})
         };
 
-        // 只更新我们管理的字段
         let mut updated = existing;
+
+        // 移除过期字段
+        if let Some(obj) = updated.as_object_mut() {
+            if stale.api_key_helper {
+                obj.remove("apiKeyHelper");
+            }
+            if let Some(env_obj) = obj.get_mut("env").and_then(|v| v.as_object_mut()) {
+                for key in &stale.env {
+                    env_obj.remove(key);
+                }
+            }
+        }
+
+        // 只更新我们管理的字段
         if let Some(helper) = &self.api_key_helper
             && let Some(obj) = updated.as_object_mut()
         {
@@ -81,34 +224,26 @@ impl ClaudeSettings {
             {
                 obj.insert("env".to_string(), serde_json::json!({}));
             }
-            if let Some(url) = &env.anthropic_base_url
-                && let Some(obj) = updated.as_object_mut()
-                && let Some(env_obj) = obj.get_mut("env").and_then(|v| v.as_object_mut())
-            {
-                env_obj.insert("ANTHROPIC_BASE_URL".to_string(), serde_json::json!(url));
-            }
-            if let Some(key) = &env.anthropic_api_key
-                && let Some(obj) = updated.as_object_mut()
+            if let Some(obj) = updated.as_object_mut()
                 && let Some(env_obj) = obj.get_mut("env").and_then(|v| v.as_object_mut())
             {
-                env_obj.insert("ANTHROPIC_AUTH_TOKEN".to_string(), serde_json::json!(key));
+                if let Some(url) = &env.anthropic_base_url {
+                    env_obj.insert(BASE_URL_KEY.to_string(), serde_json::json!(url));
+                }
+                if let Some(key) = &env.anthropic_api_key {
+                    env_obj.insert(AUTH_TOKEN_KEY.to_string(), serde_json::json!(key));
+                }
+                for (key, value) in &env.extra {
+                    env_obj.insert(key.clone(), value.clone());
+                }
             }
         }
 
         let content = serde_json::to_string_pretty(&updated).context("序列化失败")?;
-        std::fs::write(&path, content).context("写入 settings 失败")
-    }
-
-    /// 备份当前配置文件
-    ///
-    /// # Errors
-    ///
-    /// 返回文件复制错误
-    pub fn backup(&self) -> Result<std::path::PathBuf> {
-        let path = get_claude_settings_path();
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
-        let backup_path = path.with_file_name(format!("settings.json.backup.{}", timestamp));
-        std::fs::copy(&path, &backup_path).context("备份失败")?;
-        Ok(backup_path)
+        if scope == SettingsScope::Project {
+            write_atomic(path, content.as_bytes()).context("写入 settings 失败")
+        } else {
+            write_private(path, content.as_bytes()).context("写入 settings 失败")
+        }
     }
 }
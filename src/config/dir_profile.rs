@@ -0,0 +1,129 @@
+//! 目录配置模块
+//!
+//! 读取目录中指定配置档案的 `.cccrs` / `.cccrs.toml` 文件，供 shell hook 自动切换配置使用
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use super::paths::validate_profile_name;
+
+/// 只包含配置名称的目录配置文件
+pub const DIR_PROFILE_FILE: &str = ".cccrs";
+
+/// TOML 格式的目录配置文件（读取 `profile` 字段）
+pub const DIR_PROFILE_TOML_FILE: &str = ".cccrs.toml";
+
+/// 目录配置文件指定的配置档案
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirProfile {
+    /// 目录配置文件路径
+    pub path: PathBuf,
+    /// 配置名称
+    pub profile: String,
+}
+
+/// 从 `start` 向上查找第一个包含目录配置文件的目录，同一目录中 `.cccrs` 优先
+///
+/// # Errors
+///
+/// 返回文件读取失败、文件中没有配置名称或配置名称无效的错误
+pub fn find_dir_profile(start: &Path) -> anyhow::Result<Option<DirProfile>> {
+    let Some(path) = start.ancestors().find_map(|dir| {
+        [DIR_PROFILE_FILE, DIR_PROFILE_TOML_FILE]
+            .iter()
+            .map(|file| dir.join(file))
+            .find(|path| path.is_file())
+    }) else {
+        return Ok(None);
+    };
+
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("读取 {} 失败", path.display()))?;
+    let profile = if path
+        .file_name()
+        .is_some_and(|name| name == DIR_PROFILE_TOML_FILE)
+    {
+        parse_toml(&content)
+    } else {
+        parse_plain(&content)
+    }
+    .with_context(|| format!("{} 中没有指定配置名称", path.display()))?;
+    if !validate_profile_name(&profile) {
+        anyhow::bail!("{} 中的配置名称 '{}' 无效", path.display(), profile);
+    }
+    Ok(Some(DirProfile { path, profile }))
+}
+
+/// 读取 `.cccrs`：第一个非空且不以 `#` 开头的行
+fn parse_plain(content: &str) -> Option<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+/// 读取 `.cccrs.toml` 顶层的 `profile = "NAME"`
+fn parse_toml(content: &str) -> Option<String> {
+    for line in content.lines().map(str::trim) {
+        // 只读取顶层字段
+        if line.starts_with('[') {
+            return None;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() != "profile" {
+            continue;
+        }
+        let value = value.trim();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        return value
+            .strip_prefix(quote)
+            .and_then(|rest| rest.split_once(quote))
+            .map(|(name, _)| name.to_string());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_dir_profile() -> anyhow::Result<()> {
+        let temp_dir = tempfile::TempDir::new().context("创建临时目录失败")?;
+        let client = temp_dir.path().join("client");
+        let nested = client.join("src").join("app");
+        std::fs::create_dir_all(&nested).context("创建目录失败")?;
+
+        if find_dir_profile(&nested)?.is_some_and(|found| found.path.starts_with(temp_dir.path())) {
+            anyhow::bail!("没有目录配置文件时不应找到配置");
+        }
+
+        std::fs::write(
+            client.join(DIR_PROFILE_TOML_FILE),
+            "# 客户配置\nprofile = \"kimi\" # 注释\n\n[other]\nprofile = \"glm\"\n",
+        )
+        .context("写入文件失败")?;
+        let found = find_dir_profile(&nested)?.context("应找到 .cccrs.toml")?;
+        if found.profile != "kimi" || found.path != client.join(DIR_PROFILE_TOML_FILE) {
+            anyhow::bail!("查找结果不匹配: {:?}", found);
+        }
+
+        // 更近的目录和 .cccrs 优先
+        std::fs::write(nested.join(DIR_PROFILE_FILE), "# 注释\n  glm  \n")
+            .context("写入文件失败")?;
+        let nearer = find_dir_profile(&nested)?.context("应找到 .cccrs")?;
+        if nearer.profile != "glm" {
+            anyhow::bail!("应使用更近目录中的配置: {:?}", nearer);
+        }
+
+        std::fs::write(nested.join(DIR_PROFILE_FILE), "bad name\n").context("写入文件失败")?;
+        if find_dir_profile(&nested).is_ok() {
+            anyhow::bail!("无效的配置名称应返回错误");
+        }
+        Ok(())
+    }
+}
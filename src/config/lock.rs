@@ -0,0 +1,123 @@
+//! 配置锁模块
+//!
+//! 提供跨进程的建议锁，保护 `~/.claude` 下配置文件的读取-修改-写入过程
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use super::paths::get_lock_path;
+
+/// 默认获取锁的超时时间
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 覆盖锁超时时间（秒）的环境变量
+pub const LOCK_TIMEOUT_ENV: &str = "CCCRS_LOCK_TIMEOUT";
+
+/// 轮询锁的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 获取配置锁时的错误
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    /// 在超时时间内未能获取锁
+    #[error(
+        "等待配置锁超时 ({} 秒): 另一个 cccrs 进程可能正在修改配置，锁文件: {}",
+        .timeout.as_secs_f64(),
+        .path.display()
+    )]
+    Timeout {
+        /// 锁文件路径
+        path: PathBuf,
+        /// 等待时长
+        timeout: Duration,
+    },
+    /// 打开或锁定锁文件失败
+    #[error("无法获取配置锁 {}: {source}", .path.display())]
+    Io {
+        /// 锁文件路径
+        path: PathBuf,
+        /// 底层 IO 错误
+        source: std::io::Error,
+    },
+}
+
+impl LockError {
+    /// 错误码
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::Timeout { .. } => "lock_timeout",
+            Self::Io { .. } => "lock_failed",
+        }
+    }
+}
+
+/// 配置锁
+///
+/// 持有期间其他 cccrs 进程无法修改配置，释放时自动解锁
+#[derive(Debug)]
+pub struct ConfigLock {
+    _file: File,
+}
+
+impl ConfigLock {
+    /// 获取配置锁
+    ///
+    /// 超时时间默认为 [`DEFAULT_LOCK_TIMEOUT`]，可通过 `CCCRS_LOCK_TIMEOUT` 环境变量（秒）覆盖
+    ///
+    /// # Errors
+    ///
+    /// 超时或锁文件无法打开时返回 [`LockError`]
+    pub fn acquire() -> Result<Self, LockError> {
+        let timeout = std::env::var(LOCK_TIMEOUT_ENV)
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .unwrap_or(DEFAULT_LOCK_TIMEOUT);
+        Self::acquire_with_timeout(timeout)
+    }
+
+    /// 在指定超时时间内获取配置锁
+    ///
+    /// # Errors
+    ///
+    /// 超时或锁文件无法打开时返回 [`LockError`]
+    pub fn acquire_with_timeout(timeout: Duration) -> Result<Self, LockError> {
+        let path = get_lock_path().map_err(|source| LockError::Io {
+            path: PathBuf::from(super::paths::LOCK_FILE),
+            source,
+        })?;
+        let io_error = |source| LockError::Io {
+            path: path.clone(),
+            source,
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(io_error)?;
+
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) => {
+                    if start.elapsed() >= timeout {
+                        return Err(LockError::Timeout { path, timeout });
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                Err(TryLockError::Error(source)) => return Err(io_error(source)),
+            }
+        }
+    }
+}
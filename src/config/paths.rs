@@ -2,7 +2,10 @@
 //!
 //! 提供 Claude settings 和 CCCRS 配置文件的路径获取功能
 
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 use home::home_dir;
 
@@ -12,29 +15,189 @@ pub const CLAUDE_DIR: &str = ".claude";
 /// Claude settings.json 文件名
 pub const SETTINGS_FILE: &str = "settings.json";
 
+/// Claude 项目级本地 settings 文件名（不提交到版本库）
+pub const SETTINGS_LOCAL_FILE: &str = "settings.local.json";
+
 /// CCCRS 配置文件名
 pub const CCC_CONFIG_FILE: &str = "cccrs-config.json";
 
-/// 获取 Claude settings.json 的路径
+/// CCCRS 备份目录名
+pub const BACKUP_DIR: &str = "cccrs-backups";
+
+/// 加密存储文件名
+pub const VAULT_FILE: &str = "cccrs-vault.json";
+
+/// CCCRS 锁文件名
+pub const LOCK_FILE: &str = "cccrs.lock";
+
+/// 指定 Claude 配置目录的环境变量（与 Claude Code 一致）
+pub const CLAUDE_CONFIG_DIR_ENV: &str = "CLAUDE_CONFIG_DIR";
+
+/// 指定 CCCRS 配置文件路径的环境变量
+pub const CCCRS_CONFIG_ENV: &str = "CCCRS_CONFIG";
+
+/// 通过 `--config-dir` 指定的 Claude 配置目录
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// 设置 Claude 配置目录（对应 `--config-dir` 参数）
 ///
-/// 返回 `~/.claude/settings.json`
+/// 优先级高于 `CLAUDE_CONFIG_DIR` 环境变量，只在第一次调用时生效
+pub fn set_config_dir_override(dir: PathBuf) {
+    let _ = CONFIG_DIR_OVERRIDE.set(dir);
+}
+
+/// 获取 `--config-dir` 参数指定的 Claude 配置目录
 #[must_use]
-pub fn get_claude_settings_path() -> PathBuf {
+pub fn config_dir_override() -> Option<&'static Path> {
+    CONFIG_DIR_OVERRIDE.get().map(PathBuf::as_path)
+}
+
+/// 读取非空的环境变量路径
+#[must_use]
+fn env_path(key: &str) -> Option<PathBuf> {
+    std::env::var_os(key)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// 获取 Claude 配置目录
+///
+/// 优先级：`--config-dir` 参数 > `CLAUDE_CONFIG_DIR` 环境变量 > `~/.claude`
+///
+/// # Errors
+///
+/// 以上均未指定且无法确定用户主目录时返回错误
+pub fn get_claude_dir() -> std::io::Result<PathBuf> {
+    if let Some(dir) = CONFIG_DIR_OVERRIDE.get() {
+        return Ok(dir.clone());
+    }
+    if let Some(dir) = env_path(CLAUDE_CONFIG_DIR_ENV) {
+        return Ok(dir);
+    }
     home_dir()
-        .unwrap_or_default()
-        .join(CLAUDE_DIR)
-        .join(SETTINGS_FILE)
+        .filter(|home| !home.as_os_str().is_empty())
+        .map(|home| home.join(CLAUDE_DIR))
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "无法确定用户主目录，请设置 HOME 或 CLAUDE_CONFIG_DIR 环境变量，或使用 --config-dir 参数",
+            )
+        })
 }
 
-/// 获取 CCCRS 配置文件的路径
+/// 获取 Claude settings.json 的路径
+///
+/// 返回 `<Claude 配置目录>/settings.json`，默认为 `~/.claude/settings.json`
+///
+/// # Errors
+///
+/// 无法确定 Claude 配置目录时返回错误
+pub fn get_claude_settings_path() -> std::io::Result<PathBuf> {
+    Ok(get_claude_dir()?.join(SETTINGS_FILE))
+}
+
+/// Claude settings 的作用范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingsScope {
+    /// 用户级：`~/.claude/settings.json`（或 `CLAUDE_CONFIG_DIR` 下的 settings.json）
+    #[default]
+    User,
+    /// 项目级：`<项目>/.claude/settings.json`
+    Project,
+    /// 项目本地：`<项目>/.claude/settings.local.json`
+    Local,
+}
+
+impl SettingsScope {
+    /// 获取该作用范围对应的 settings 文件路径
+    ///
+    /// 项目级作用范围从当前目录向上查找项目根目录
+    ///
+    /// # Errors
+    ///
+    /// 返回当前目录获取错误；项目级作用范围找不到项目根目录时返回 `NotFound` 错误
+    pub fn settings_path(self) -> std::io::Result<PathBuf> {
+        let file = match self {
+            Self::User => return get_claude_settings_path(),
+            Self::Project => SETTINGS_FILE,
+            Self::Local => SETTINGS_LOCAL_FILE,
+        };
+        let root = find_project_root(&std::env::current_dir()?).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "未找到项目根目录（包含 .git 或 .claude/settings*.json 的目录），\
+                 请在项目目录中运行或使用 --scope user",
+            )
+        })?;
+        Ok(root.join(CLAUDE_DIR).join(file))
+    }
+}
+
+/// 从 `start` 向上查找项目根目录
 ///
-/// 返回 `~/.claude/cccrs-config.json`
+/// 项目根目录为第一个包含 `.git` 或 `.claude/settings*.json` 的目录（用户主目录除外，
+/// 避免把用户级 settings.json 当作项目 settings）；找不到时返回 `None`
 #[must_use]
-pub fn get_ccc_config_path() -> PathBuf {
-    home_dir()
-        .unwrap_or_default()
-        .join(CLAUDE_DIR)
-        .join(CCC_CONFIG_FILE)
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    let home = home_dir();
+    start
+        .ancestors()
+        .filter(|dir| home.as_deref() != Some(*dir))
+        .find(|dir| {
+            let claude_dir = dir.join(CLAUDE_DIR);
+            dir.join(".git").exists()
+                || claude_dir.join(SETTINGS_FILE).is_file()
+                || claude_dir.join(SETTINGS_LOCAL_FILE).is_file()
+        })
+        .map(Path::to_path_buf)
+}
+
+/// 获取 CCCRS 配置文件的路径
+///
+/// 优先使用 `CCCRS_CONFIG` 环境变量，否则返回 `<Claude 配置目录>/cccrs-config.json`
+///
+/// # Errors
+///
+/// 无法确定 Claude 配置目录时返回错误
+pub fn get_ccc_config_path() -> std::io::Result<PathBuf> {
+    if let Some(path) = env_path(CCCRS_CONFIG_ENV) {
+        return Ok(path);
+    }
+    Ok(get_claude_dir()?.join(CCC_CONFIG_FILE))
+}
+
+/// 获取 settings 备份目录的路径
+///
+/// 返回 `<Claude 配置目录>/cccrs-backups`
+///
+/// # Errors
+///
+/// 无法确定 Claude 配置目录时返回错误
+pub fn get_backup_dir() -> std::io::Result<PathBuf> {
+    Ok(get_claude_dir()?.join(BACKUP_DIR))
+}
+
+/// 获取 CCCRS 锁文件的路径
+///
+/// 返回 `<Claude 配置目录>/cccrs.lock`
+///
+/// # Errors
+///
+/// 无法确定 Claude 配置目录时返回错误
+pub fn get_lock_path() -> std::io::Result<PathBuf> {
+    Ok(get_claude_dir()?.join(LOCK_FILE))
+}
+
+/// 获取加密存储文件的路径
+///
+/// 返回 `<Claude 配置目录>/cccrs-vault.json`
+///
+/// # Errors
+///
+/// 无法确定 Claude 配置目录时返回错误
+pub fn get_vault_path() -> std::io::Result<PathBuf> {
+    Ok(get_claude_dir()?.join(VAULT_FILE))
 }
 
 /// 确保 CCCRS 配置文件存在
@@ -45,13 +208,13 @@ pub fn get_ccc_config_path() -> PathBuf {
 ///
 /// 返回文件或目录创建错误
 pub fn ensure_ccc_config_exists() -> std::io::Result<()> {
-    let path = get_ccc_config_path();
+    let path = get_ccc_config_path()?;
     if !path.exists() {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
         let initial_content = r#"{"profiles": {},"current": null}"#;
-        std::fs::write(&path, initial_content)?;
+        super::atomic::write_private(&path, initial_content.as_bytes())?;
     }
     Ok(())
 }
@@ -93,15 +256,52 @@ mod tests {
         assert_eq!(CLAUDE_DIR, ".claude");
         assert_eq!(SETTINGS_FILE, "settings.json");
         assert_eq!(CCC_CONFIG_FILE, "cccrs-config.json");
+        assert_eq!(BACKUP_DIR, "cccrs-backups");
+    }
+
+    #[test]
+    fn test_find_project_root() -> anyhow::Result<()> {
+        use anyhow::Context;
+
+        let temp_dir = tempfile::TempDir::new().context("创建临时目录失败")?;
+        let project = temp_dir.path().join("project");
+        let nested = project.join("src").join("deep");
+        std::fs::create_dir_all(&nested).context("创建目录失败")?;
+        std::fs::create_dir_all(project.join(".git")).context("创建目录失败")?;
+
+        if find_project_root(&nested).as_ref() != Some(&project) {
+            anyhow::bail!("项目根目录不匹配");
+        }
+
+        // 没有项目标记时不回退到起始目录
+        let other = temp_dir.path().join("other");
+        std::fs::create_dir_all(&other).context("创建目录失败")?;
+        if find_project_root(&other).is_some_and(|root| root.starts_with(temp_dir.path())) {
+            anyhow::bail!("无项目标记时不应找到项目根目录");
+        }
+        Ok(())
     }
 
     #[test]
-    fn test_path_functions() {
-        // 验证路径函数返回正确的路径
-        let settings_path = get_claude_settings_path();
-        assert!(settings_path.ends_with(".claude/settings.json"));
+    fn test_path_functions() -> anyhow::Result<()> {
+        // 验证路径函数返回正确的路径（开发环境可能设置了 CLAUDE_CONFIG_DIR 或 CCCRS_CONFIG）
+        let claude_dir = get_claude_dir()?;
+        let settings_path = get_claude_settings_path()?;
+        if settings_path != claude_dir.join(SETTINGS_FILE) {
+            anyhow::bail!("settings 路径不匹配: {}", settings_path.display());
+        }
+
+        let ccc_config_path = get_ccc_config_path()?;
+        let expected_config =
+            env_path(CCCRS_CONFIG_ENV).unwrap_or_else(|| claude_dir.join(CCC_CONFIG_FILE));
+        if ccc_config_path != expected_config {
+            anyhow::bail!("配置文件路径不匹配: {}", ccc_config_path.display());
+        }
 
-        let ccc_config_path = get_ccc_config_path();
-        assert!(ccc_config_path.ends_with(".claude/cccrs-config.json"));
+        let backup_dir = get_backup_dir()?;
+        if backup_dir != claude_dir.join(BACKUP_DIR) {
+            anyhow::bail!("备份目录不匹配: {}", backup_dir.display());
+        }
+        Ok(())
     }
 }
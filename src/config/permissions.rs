@@ -0,0 +1,125 @@
+//! 文件权限模块
+//!
+//! 检查并修复包含 API Key 的文件的权限：cccrs 配置文件、加密存储、settings 文件及其备份
+//! 应只允许当前用户读写 (0600)。非 Unix 平台不检查权限
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::{
+    backup::list_backups,
+    cccrs_config::is_secret_env_key,
+    claude_settings::ClaudeSettings,
+    paths::{SettingsScope, get_ccc_config_path, get_claude_settings_path, get_vault_path},
+};
+
+/// 包含敏感信息的文件的权限
+pub const PRIVATE_MODE: u32 = 0o600;
+
+/// 可被其他用户访问的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsecureFile {
+    /// 文件路径
+    pub path: PathBuf,
+    /// 当前权限（仅权限位）
+    pub mode: u32,
+}
+
+/// 列出所有已存在的、包含敏感信息的文件
+///
+/// 包括当前项目的 `settings.local.json`；`settings.json` 通常会提交到仓库，只在其中包含敏感的环境变量时才检查
+///
+/// # Errors
+///
+/// 返回路径获取或备份目录读取错误
+pub fn secret_files() -> Result<Vec<PathBuf>> {
+    let mut files = vec![
+        get_ccc_config_path()?,
+        get_vault_path()?,
+        get_claude_settings_path()?,
+    ];
+    // 不在项目目录中时没有项目级 settings
+    files.extend(SettingsScope::Local.settings_path().ok());
+    files.extend(
+        SettingsScope::Project
+            .settings_path()
+            .ok()
+            .filter(|path| has_secret_env(path)),
+    );
+    files.extend(list_backups()?.into_iter().map(|backup| backup.path));
+    files.retain(|path| path.is_file());
+    files.dedup();
+    Ok(files)
+}
+
+/// settings 文件中是否包含敏感的环境变量（无法读取时视为不包含）
+fn has_secret_env(path: &Path) -> bool {
+    ClaudeSettings::load_from(path)
+        .ok()
+        .and_then(|settings| settings.env)
+        .is_some_and(|env| env.vars().keys().any(|key| is_secret_env_key(key)))
+}
+
+/// 找出可被同组或其他用户访问的敏感文件
+///
+/// # Errors
+///
+/// 返回路径获取、目录读取或文件元数据读取错误
+#[cfg(unix)]
+pub fn find_insecure_files() -> Result<Vec<InsecureFile>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    use anyhow::Context;
+
+    let mut insecure = Vec::new();
+    for path in secret_files()? {
+        let mode = std::fs::metadata(&path)
+            .with_context(|| format!("读取文件权限失败: {}", path.display()))?
+            .permissions()
+            .mode()
+            & 0o777;
+        if mode & 0o077 != 0 {
+            insecure.push(InsecureFile { path, mode });
+        }
+    }
+    Ok(insecure)
+}
+
+/// 找出可被同组或其他用户访问的敏感文件（非 Unix 平台不检查）
+///
+/// # Errors
+///
+/// 不会返回错误
+#[cfg(not(unix))]
+pub const fn find_insecure_files() -> Result<Vec<InsecureFile>> {
+    Ok(Vec::new())
+}
+
+/// 将文件权限修改为 0600
+///
+/// # Errors
+///
+/// 返回权限修改错误
+#[cfg(unix)]
+pub fn fix_permissions(files: &[InsecureFile]) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    use anyhow::Context;
+
+    for file in files {
+        std::fs::set_permissions(&file.path, std::fs::Permissions::from_mode(PRIVATE_MODE))
+            .with_context(|| format!("修改文件权限失败: {}", file.path.display()))?;
+    }
+    Ok(())
+}
+
+/// 将文件权限修改为 0600（非 Unix 平台无需处理）
+///
+/// # Errors
+///
+/// 不会返回错误
+#[cfg(not(unix))]
+pub const fn fix_permissions(_files: &[InsecureFile]) -> Result<()> {
+    Ok(())
+}
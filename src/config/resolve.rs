@@ -0,0 +1,264 @@
+//! 配置档案解析模块
+//!
+//! 将配置档案解析为最终需要设置的环境变量，并展开字段值中的
+//! `${env:NAME}`、`${file:PATH}`、`${cmd:COMMAND}`、`${vault:ID}` 占位符。
+//! 其他以 `${` 开头的内容（例如 Token 中恰好包含的 `${`）保持不变
+
+use std::{collections::BTreeMap, path::PathBuf, process::Command};
+
+use anyhow::{Context, Result};
+
+use super::{
+    cccrs_config::{API_KEY_KEY, Profile},
+    vault::{self, VAULT_PLACEHOLDER_KIND},
+};
+
+/// 占位符起始标记
+const PLACEHOLDER_START: &str = "${";
+
+/// 支持的占位符类型
+const PLACEHOLDER_KINDS: [&str; 4] = ["env", "file", "cmd", VAULT_PLACEHOLDER_KIND];
+
+/// 解析配置档案的环境变量
+///
+/// 包含档案中的所有环境变量；如果配置了 `apiKeyHelper` 且档案未设置 `ANTHROPIC_API_KEY`，
+/// 会执行该命令并将其输出作为 `ANTHROPIC_API_KEY`
+///
+/// # Errors
+///
+/// 返回 `apiKeyHelper` 执行失败错误
+pub fn resolve_env(profile: &Profile) -> Result<BTreeMap<String, String>> {
+    let mut vars: BTreeMap<String, String> = profile
+        .env
+        .vars()
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    if let Some(helper) = &profile.api_key_helper
+        && !vars.contains_key(API_KEY_KEY)
+    {
+        vars.insert(API_KEY_KEY.to_string(), run_helper(helper)?);
+    }
+
+    Ok(vars)
+}
+
+/// 展开配置档案环境变量值中的占位符
+///
+/// 在 `use`、`exec`、`env` 实际使用配置档案时调用，`apiKeyHelper` 本身就是命令，不做展开
+///
+/// # Errors
+///
+/// 返回占位符格式错误或解析失败错误，错误信息包含配置档案名称和字段名
+pub fn expand_profile(name: &str, profile: &Profile) -> Result<Profile> {
+    let mut expanded = profile.clone();
+    for (key, value) in profile.env.vars() {
+        if has_placeholder(value) {
+            let value = expand_placeholders(value)
+                .with_context(|| format!("解析配置档案 '{}' 的字段 '{}' 失败", name, key))?;
+            expanded.env.set(key, value);
+        }
+    }
+    Ok(expanded)
+}
+
+/// 配置档案的值是否来自命令输出（`apiKeyHelper` 或 `${cmd:}` 占位符）
+///
+/// 这类值通常是短期有效的 Token，长时间运行时不应一直复用
+#[must_use]
+pub fn uses_command(profile: &Profile) -> bool {
+    let cmd_prefix = format!("{}cmd:", PLACEHOLDER_START);
+    profile.api_key_helper.is_some()
+        || profile
+            .env
+            .vars()
+            .values()
+            .any(|value| value.contains(&cmd_prefix))
+}
+
+/// 值中是否包含占位符
+#[must_use]
+pub fn has_placeholder(value: &str) -> bool {
+    value.split(PLACEHOLDER_START).skip(1).any(is_placeholder)
+}
+
+/// `${` 之后的内容是否以支持的占位符类型和 `:` 开头
+fn is_placeholder(after: &str) -> bool {
+    PLACEHOLDER_KINDS.iter().any(|kind| {
+        after
+            .strip_prefix(kind)
+            .is_some_and(|rest| rest.starts_with(':'))
+    })
+}
+
+/// 展开字符串中的所有占位符，占位符以外的内容（包括不属于支持类型的 `${`）保持不变
+///
+/// 占位符中可以包含成对的 `{}`（例如 `${cmd:awk '{print $1}' f}`），以匹配的 `}` 结束
+///
+/// # Errors
+///
+/// 返回占位符格式错误或解析失败错误
+pub fn expand_placeholders(value: &str) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some((before, after)) = rest.split_once(PLACEHOLDER_START) {
+        result.push_str(before);
+        if !is_placeholder(after) {
+            result.push_str(PLACEHOLDER_START);
+            rest = after;
+            continue;
+        }
+        let (placeholder, remaining) = split_placeholder(after).ok_or_else(|| {
+            anyhow::anyhow!(
+                "占位符缺少结束的 '}}'（占位符中的 '{{' 和 '}}' 必须成对）: ${{{}",
+                after
+            )
+        })?;
+        result.push_str(&expand_placeholder(placeholder)?);
+        rest = remaining;
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// 在 `${` 之后的内容中找到与之匹配的 `}`，返回占位符内容和 `}` 之后的剩余部分
+fn split_placeholder(after: &str) -> Option<(&str, &str)> {
+    let mut depth = 0usize;
+    for (index, ch) in after.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' if depth == 0 => {
+                let (placeholder, remaining) = after.split_at(index);
+                return Some((placeholder, remaining.strip_prefix('}')?));
+            }
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 展开单个占位符（不含 `${` 和 `}`）
+fn expand_placeholder(placeholder: &str) -> Result<String> {
+    let (kind, arg) = placeholder
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("无效的占位符 '${{{}}}'", placeholder))?;
+    match kind {
+        "env" => std::env::var(arg).with_context(|| format!("环境变量 '{}' 未设置", arg)),
+        "file" => {
+            let path = expand_tilde(arg);
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("读取文件失败: {}", path.display()))?;
+            Ok(content.trim_end_matches(['\r', '\n']).to_string())
+        }
+        "cmd" => run_command(arg, "占位符命令"),
+        VAULT_PLACEHOLDER_KIND => vault::lookup(arg),
+        _ => anyhow::bail!("未知的占位符类型 '{}' (可用: env, file, cmd, vault)", kind),
+    }
+}
+
+/// 将以 `~/` 开头的路径展开为用户主目录下的路径
+#[must_use]
+fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// 执行 `apiKeyHelper` 命令并返回去除首尾空白后的输出
+///
+/// # Errors
+///
+/// 返回命令启动失败、退出码非零或输出为空错误
+pub fn run_helper(helper: &str) -> Result<String> {
+    run_command(helper, "apiKeyHelper")
+}
+
+/// 执行 shell 命令并返回去除首尾空白后的输出，`label` 用于错误信息
+fn run_command(command: &str, label: &str) -> Result<String> {
+    let output = shell_command(command)
+        .output()
+        .with_context(|| format!("执行 {} 失败: {}", label, command))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} 退出码非零 ({}): {}",
+            label,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .with_context(|| format!("{} 输出不是有效的 UTF-8", label))?
+        .trim()
+        .to_string();
+    if stdout.is_empty() {
+        anyhow::bail!("{} 没有输出任何内容: {}", label, command);
+    }
+    Ok(stdout)
+}
+
+/// 构造通过系统 shell 执行命令的 [`Command`]
+#[must_use]
+pub fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_placeholders() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().context("创建临时目录失败")?;
+        let secret_path = temp_dir.path().join("secret");
+        std::fs::write(&secret_path, "sk-from-file\n").context("写入文件失败")?;
+
+        let expanded = expand_placeholders(&format!("Bearer ${{file:{}}}", secret_path.display()))?;
+        if expanded != "Bearer sk-from-file" {
+            anyhow::bail!("文件占位符展开结果不匹配: {}", expanded);
+        }
+        if expand_placeholders("plain-value")? != "plain-value" {
+            anyhow::bail!("不含占位符的值不应改变");
+        }
+        // 不属于支持类型的 `${` 原样保留
+        for literal in ["sk-abc${weird", "${novalue}", "${unknown:x}", "a${b}${"] {
+            if has_placeholder(literal) || expand_placeholders(literal)? != literal {
+                anyhow::bail!("'{}' 应原样保留", literal);
+            }
+        }
+        if !has_placeholder("sk-${weird-${env:HOME}") {
+            anyhow::bail!("应识别其他内容之后的占位符");
+        }
+        if cfg!(unix) && expand_placeholders("${cmd:echo sk-from-cmd}")? != "sk-from-cmd" {
+            anyhow::bail!("命令占位符展开结果不匹配");
+        }
+        // 命令中成对的 `{}` 不会提前结束占位符
+        if cfg!(unix)
+            && expand_placeholders("${cmd:echo sk-a sk-b | awk '{print $2}'}/${cmd:printf '{a}'}")?
+                != "sk-b/{a}"
+        {
+            anyhow::bail!("包含花括号的命令占位符展开结果不匹配");
+        }
+        for invalid in [
+            "${env:CCCRS_TEST_UNSET_VARIABLE}",
+            "${env:UNCLOSED",
+            "${cmd:awk '{print $1' f}",
+            "${file:/nonexistent/cccrs-test-file}",
+        ] {
+            if expand_placeholders(invalid).is_ok() {
+                anyhow::bail!("无效占位符 '{}' 应返回错误", invalid);
+            }
+        }
+        Ok(())
+    }
+}
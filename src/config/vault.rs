@@ -0,0 +1,426 @@
+//! 加密存储模块
+//!
+//! 将配置档案中的敏感字段加密保存在 `~/.claude/cccrs-vault.json`，配置文件中只保留
+//! `${vault:ID}` 占位符。加密密钥由口令经 Argon2id 派生，使用 XChaCha20-Poly1305 加密；
+//! 口令从 `CCCRS_PASSPHRASE` 环境变量读取，未设置时交互式输入
+
+use std::{
+    collections::BTreeMap,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, PoisonError},
+};
+
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::{
+    Key, KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, AeadCore, OsRng, Payload, rand_core::RngCore},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CccError, STDIN_NOT_TERMINAL};
+
+use super::{atomic::write_private, paths::get_vault_path};
+
+/// 提供口令的环境变量
+pub const PASSPHRASE_ENV: &str = "CCCRS_PASSPHRASE";
+
+/// 加密存储占位符类型
+pub const VAULT_PLACEHOLDER_KIND: &str = "vault";
+
+/// 加密存储文件格式版本
+const VAULT_VERSION: u32 = 1;
+
+/// 附加认证数据，绑定文件格式版本
+const VAULT_AAD: &[u8] = b"cccrs-vault-v1";
+
+/// 盐长度（字节）
+const SALT_LEN: usize = 16;
+
+/// 已解锁的加密存储内容（每个进程最多输入一次口令）
+static UNLOCKED: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+
+/// 串行化解锁过程，避免多个线程同时读取口令和派生密钥
+static UNLOCKING: Mutex<()> = Mutex::new(());
+
+/// Argon2id 密钥派生参数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct KdfParams {
+    /// Base64 编码的盐
+    salt: String,
+    /// 内存开销（KiB）
+    memory_kib: u32,
+    /// 迭代次数
+    iterations: u32,
+    /// 并行度
+    parallelism: u32,
+}
+
+/// 加密存储文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultFile {
+    /// 文件格式版本
+    version: u32,
+    /// 密钥派生参数
+    kdf: KdfParams,
+    /// Base64 编码的随机数
+    nonce: String,
+    /// Base64 编码的密文（明文为 `ID -> 敏感值` 的 JSON 对象）
+    ciphertext: String,
+}
+
+/// 已解锁的加密存储
+pub struct Vault {
+    /// 存储文件路径
+    path: PathBuf,
+    /// 密钥派生参数
+    kdf: KdfParams,
+    /// 由口令派生的密钥
+    key: Key,
+    /// `ID -> 敏感值`
+    secrets: BTreeMap<String, String>,
+}
+
+impl Vault {
+    /// 加密存储文件是否存在
+    ///
+    /// # Errors
+    ///
+    /// 无法确定 Claude 配置目录时返回错误
+    pub fn exists() -> Result<bool> {
+        Ok(get_vault_path()?.exists())
+    }
+
+    /// 使用新口令创建空的加密存储（调用 [`Vault::save`] 后才会写入文件）
+    ///
+    /// # Errors
+    ///
+    /// 返回路径获取或密钥派生错误
+    pub fn create(passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let params = Params::default();
+        let kdf = KdfParams {
+            salt: BASE64.encode(salt),
+            memory_kib: params.m_cost(),
+            iterations: params.t_cost(),
+            parallelism: params.p_cost(),
+        };
+        Ok(Self {
+            path: get_vault_path()?,
+            key: derive_key(passphrase, &kdf)?,
+            kdf,
+            secrets: BTreeMap::new(),
+        })
+    }
+
+    /// 使用口令解锁加密存储
+    ///
+    /// # Errors
+    ///
+    /// 返回存储不存在、文件格式错误、口令错误等错误
+    pub fn unlock(passphrase: &str) -> Result<Self> {
+        Self::unlock_from(get_vault_path()?, passphrase)
+    }
+
+    /// 使用口令解锁指定路径的加密存储
+    ///
+    /// # Errors
+    ///
+    /// 返回存储不存在、文件格式错误、口令错误等错误
+    pub fn unlock_from(path: PathBuf, passphrase: &str) -> Result<Self> {
+        if !path.exists() {
+            return Err(CccError::VaultNotFound.into());
+        }
+        let content = std::fs::read_to_string(&path).context("读取加密存储失败")?;
+        let file: VaultFile = serde_json::from_str(&content).context("解析加密存储失败")?;
+        if file.version != VAULT_VERSION {
+            anyhow::bail!("不支持的加密存储版本: {}", file.version);
+        }
+
+        let key = derive_key(passphrase, &file.kdf)?;
+        let nonce = BASE64.decode(&file.nonce).context("解析加密存储失败")?;
+        let ciphertext = BASE64
+            .decode(&file.ciphertext)
+            .context("解析加密存储失败")?;
+        if nonce.len() != 24 {
+            anyhow::bail!("解析加密存储失败: 随机数长度错误");
+        }
+        let plaintext = XChaCha20Poly1305::new(&key)
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: VAULT_AAD,
+                },
+            )
+            .map_err(|_| CccError::WrongPassphrase)?;
+        let secrets = serde_json::from_slice(&plaintext).context("解析加密存储失败")?;
+
+        Ok(Self {
+            path,
+            kdf: file.kdf,
+            key,
+            secrets,
+        })
+    }
+
+    /// 加密存储文件路径
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 获取敏感值
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.secrets.get(id).map(String::as_str)
+    }
+
+    /// 保存敏感值并返回其 ID
+    ///
+    /// 优先使用 `preferred` 作为 ID；已被其他值占用时追加序号
+    pub fn insert(&mut self, preferred: &str, value: String) -> String {
+        let mut id = preferred.to_string();
+        let mut seq = 2;
+        while self
+            .secrets
+            .get(&id)
+            .is_some_and(|existing| *existing != value)
+        {
+            id = format!("{}_{}", preferred, seq);
+            seq += 1;
+        }
+        self.secrets.insert(id.clone(), value);
+        id
+    }
+
+    /// 将值中的 `${vault:ID}` 占位符替换为敏感值，其他内容保持不变
+    ///
+    /// # Errors
+    ///
+    /// 返回 ID 不存在错误
+    pub fn reveal(&self, value: &str) -> Result<String> {
+        replace_references(value, |id| {
+            self.get(id)
+                .map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("加密存储中不存在 '{}'", id))
+        })
+    }
+
+    /// 重新加密并写入存储文件（每次使用新的随机数）
+    ///
+    /// # Errors
+    ///
+    /// 返回序列化、加密或文件写入错误
+    pub fn save(&self) -> Result<()> {
+        let plaintext = serde_json::to_vec(&self.secrets).context("序列化加密存储失败")?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = XChaCha20Poly1305::new(&self.key)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: VAULT_AAD,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("加密失败"))?;
+        let file = VaultFile {
+            version: VAULT_VERSION,
+            kdf: self.kdf.clone(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let content = serde_json::to_string_pretty(&file).context("序列化加密存储失败")?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("创建 Claude 配置目录失败")?;
+        }
+        write_private(&self.path, content.as_bytes()).context("写入加密存储失败")
+    }
+}
+
+/// 由口令派生 256 位密钥
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> Result<Key> {
+    let salt = BASE64.decode(&kdf.salt).context("解析加密存储失败")?;
+    let params = Params::new(
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        Some(Params::DEFAULT_OUTPUT_LEN),
+    )
+    .map_err(|err| anyhow::anyhow!("无效的密钥派生参数: {}", err))?;
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|err| anyhow::anyhow!("派生密钥失败: {}", err))?;
+    Ok(key)
+}
+
+/// 生成引用加密存储中敏感值的占位符
+#[must_use]
+pub fn reference(id: &str) -> String {
+    format!("${{{}:{}}}", VAULT_PLACEHOLDER_KIND, id)
+}
+
+/// 列出值中引用的所有加密存储 ID
+#[must_use]
+pub fn referenced_ids(value: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    let _ = replace_references(value, |id| {
+        ids.push(id.to_string());
+        Ok(String::new())
+    });
+    ids
+}
+
+/// 替换值中的所有 `${vault:ID}` 占位符，其他占位符保持不变
+fn replace_references(
+    value: &str,
+    mut replace: impl FnMut(&str) -> Result<String>,
+) -> Result<String> {
+    let prefix = format!("${{{}:", VAULT_PLACEHOLDER_KIND);
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some((before, after)) = rest.split_once(&prefix) {
+        result.push_str(before);
+        let Some((id, remaining)) = after.split_once('}') else {
+            result.push_str(&prefix);
+            rest = after;
+            break;
+        };
+        result.push_str(&replace(id)?);
+        rest = remaining;
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// 读取加密存储口令
+///
+/// 优先使用 `CCCRS_PASSPHRASE` 环境变量；否则在终端中交互式输入，`confirm` 为 `true` 时要求输入两次
+///
+/// # Errors
+///
+/// 返回口令为空、无法交互式输入等错误
+pub fn read_passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV)
+        && !passphrase.is_empty()
+    {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(CccError::MissingInput {
+            what: "加密存储口令",
+            reason: STDIN_NOT_TERMINAL,
+            flag: PASSPHRASE_ENV,
+        }
+        .into());
+    }
+
+    let theme = dialoguer::theme::ColorfulTheme::default();
+    let mut prompt = dialoguer::Password::with_theme(&theme).with_prompt("加密存储口令");
+    if confirm {
+        prompt = prompt.with_confirmation("再次输入口令", "两次输入的口令不一致");
+    }
+    let passphrase = prompt.interact().context("读取口令失败")?;
+    if passphrase.is_empty() {
+        anyhow::bail!("口令不能为空");
+    }
+    Ok(passphrase)
+}
+
+/// 获取加密存储中的敏感值，供占位符展开使用
+///
+/// 首次调用时读取口令并解锁，同一进程内之后的调用复用解锁结果；
+/// 多个线程同时调用时只有一个线程读取口令，其他线程等待解锁结果
+///
+/// # Errors
+///
+/// 返回口令读取、解锁失败或 ID 不存在错误
+pub fn lookup(id: &str) -> Result<String> {
+    let secrets = if let Some(secrets) = UNLOCKED.get() {
+        secrets
+    } else {
+        let _guard = UNLOCKING.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(secrets) = UNLOCKED.get() {
+            secrets
+        } else {
+            let vault = Vault::unlock(&read_passphrase(false)?)?;
+            UNLOCKED.get_or_init(|| vault.secrets)
+        }
+    };
+    secrets
+        .get(id)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("加密存储中不存在 '{}'", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_references() -> Result<()> {
+        let value = "Bearer ${vault:kimi.TOKEN} ${env:HOME}";
+        if referenced_ids(value) != ["kimi.TOKEN"] {
+            anyhow::bail!("引用解析结果不匹配: {:?}", referenced_ids(value));
+        }
+        let replaced = replace_references(value, |id| Ok(id.to_lowercase()))?;
+        if replaced != "Bearer kimi.token ${env:HOME}" {
+            anyhow::bail!("替换结果不匹配: {}", replaced);
+        }
+        if reference("kimi.TOKEN") != "${vault:kimi.TOKEN}" {
+            anyhow::bail!("占位符格式不匹配");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_and_unlock() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().context("创建临时目录失败")?;
+        let kdf = KdfParams {
+            salt: BASE64.encode([7u8; SALT_LEN]),
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let mut vault = Vault {
+            path: temp_dir.path().join("vault.json"),
+            key: derive_key("correct horse", &kdf)?,
+            kdf,
+            secrets: BTreeMap::new(),
+        };
+        let first = vault.insert("kimi.TOKEN", "sk-one".to_string());
+        let second = vault.insert("kimi.TOKEN", "sk-two".to_string());
+        if first != "kimi.TOKEN" || second != "kimi.TOKEN_2" {
+            anyhow::bail!("ID 冲突处理不正确: {} {}", first, second);
+        }
+        vault.save()?;
+
+        let content = std::fs::read_to_string(vault.path()).context("读取加密存储失败")?;
+        if content.contains("sk-one") {
+            anyhow::bail!("加密存储中不应包含明文");
+        }
+        if !matches!(
+            Vault::unlock_from(vault.path().to_path_buf(), "wrong")
+                .err()
+                .and_then(|err| err.downcast::<CccError>().ok()),
+            Some(CccError::WrongPassphrase)
+        ) {
+            anyhow::bail!("错误的口令应返回 WrongPassphrase");
+        }
+        let unlocked = Vault::unlock_from(vault.path().to_path_buf(), "correct horse")?;
+        if unlocked.secrets != vault.secrets {
+            anyhow::bail!("解锁后的内容不匹配");
+        }
+        if vault.reveal("${vault:kimi.TOKEN_2}")? != "sk-two" {
+            anyhow::bail!("敏感值解析结果不匹配");
+        }
+        if vault.reveal("${vault:missing}").is_ok() {
+            anyhow::bail!("不存在的 ID 应返回错误");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+//! 错误模块
+//!
+//! 定义带有错误码的常见错误，供 JSON 输出模式使用
+
+use crate::config::LockError;
+
+/// CCCRS 常见错误
+#[derive(Debug, thiserror::Error)]
+pub enum CccError {
+    /// 配置档案不存在
+    #[error("配置档案 '{0}' 不存在")]
+    ProfileNotFound(String),
+    /// 配置档案已存在
+    #[error("配置档案 '{0}' 已存在")]
+    ProfileExists(String),
+    /// 配置名称无效
+    #[error("无效的配置名称 '{0}': 只能包含字母、数字、下划线和连字符")]
+    InvalidProfileName(String),
+    /// 环境变量名无效
+    #[error("无效的环境变量名 '{0}': 只能包含字母、数字和下划线，且不能以数字开头")]
+    InvalidEnvKey(String),
+    /// 配置档案继承存在循环
+    #[error("配置档案继承存在循环: {0}")]
+    ExtendsCycle(String),
+    /// 缺少必要信息且无法交互式输入
+    #[error("缺少 {what}: {reason}，无法交互式输入，请使用 {flag} 指定")]
+    MissingInput {
+        /// 缺少的信息
+        what: &'static str,
+        /// 无法交互式输入的原因
+        reason: &'static str,
+        /// 可用于指定该信息的参数
+        flag: &'static str,
+    },
+    /// 备份不存在
+    #[error("备份 '{0}' 不存在")]
+    BackupNotFound(String),
+    /// 加密存储不存在
+    #[error("加密存储不存在，请先运行 cccrs vault encrypt")]
+    VaultNotFound,
+    /// 加密存储口令错误
+    #[error("口令错误或加密存储已损坏")]
+    WrongPassphrase,
+    /// 配置档案及其备用配置均不可用
+    #[error("没有可用的配置档案: {0}")]
+    NoHealthyProfile(String),
+}
+
+impl CccError {
+    /// 错误码
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::ProfileNotFound(_) => "profile_not_found",
+            Self::ProfileExists(_) => "profile_exists",
+            Self::InvalidProfileName(_) => "invalid_profile_name",
+            Self::InvalidEnvKey(_) => "invalid_env_key",
+            Self::ExtendsCycle(_) => "extends_cycle",
+            Self::MissingInput { .. } => "missing_input",
+            Self::BackupNotFound(_) => "backup_not_found",
+            Self::VaultNotFound => "vault_not_found",
+            Self::WrongPassphrase => "wrong_passphrase",
+            Self::NoHealthyProfile(_) => "no_healthy_profile",
+        }
+    }
+}
+
+/// stdin 不是终端时 [`CccError::MissingInput`] 的原因
+pub const STDIN_NOT_TERMINAL: &str = "stdin 不是终端";
+
+/// 获取错误链中第一个已知错误的错误码，未知错误返回 `error`
+#[must_use]
+pub fn error_code(err: &anyhow::Error) -> &'static str {
+    err.chain()
+        .find_map(|cause| {
+            cause
+                .downcast_ref::<CccError>()
+                .map(CccError::code)
+                .or_else(|| cause.downcast_ref::<LockError>().map(LockError::code))
+        })
+        .unwrap_or("error")
+}
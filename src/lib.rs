@@ -4,4 +4,8 @@
 
 pub mod commands;
 pub mod config;
+pub mod error;
 pub mod output;
+pub mod probe;
+pub mod proxy;
+pub mod shell;
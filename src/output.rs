@@ -1,25 +1,87 @@
 //! 输出模块
 //!
-//! 提供带颜色的格式化输出函数
+//! 提供带颜色的格式化输出函数，以及 `--output json` 模式下的结构化输出
+
+use std::sync::OnceLock;
 
 use colored::Colorize;
 
+/// 输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// 人类可读的文本输出
+    #[default]
+    Text,
+    /// 结构化 JSON 输出，不输出提示信息
+    Json,
+}
+
+/// 通过 `--output` 指定的输出格式
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// 设置输出格式（对应 `--output` 参数），只在第一次调用时生效
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// 是否为 JSON 输出模式
+#[must_use]
+pub fn is_json() -> bool {
+    FORMAT.get() == Some(&OutputFormat::Json)
+}
+
+/// 输出命令的 JSON 结果 (仅 JSON 模式)
+///
+/// 格式为 `{"ok": true, "command": <命令>, "result": <结果>}`
+pub fn emit(command: &str, result: serde_json::Value) {
+    if is_json() {
+        println!(
+            "{}",
+            serde_json::json!({ "ok": true, "command": command, "result": result })
+        );
+    }
+}
+
+/// 输出错误的 JSON 结果
+///
+/// 格式为 `{"ok": false, "error": {"code": <错误码>, "message": <错误信息>}}`
+pub fn emit_error(err: &anyhow::Error) {
+    println!(
+        "{}",
+        serde_json::json!({
+            "ok": false,
+            "error": {
+                "code": crate::error::error_code(err),
+                "message": format!("{:#}", err),
+            },
+        })
+    );
+}
+
 /// 错误输出 (红色)
 pub fn error(msg: &str) {
-    eprintln!("{} {}", "错误:".red().bold(), msg);
+    if !is_json() {
+        eprintln!("{} {}", "错误:".red().bold(), msg);
+    }
 }
 
 /// 信息输出 (蓝色)
 pub fn info(msg: &str) {
-    println!("{} {}", "信息:".blue().bold(), msg);
+    if !is_json() {
+        println!("{} {}", "信息:".blue().bold(), msg);
+    }
 }
 
 /// 成功输出 (绿色)
 pub fn success(msg: &str) {
-    println!("{} {}", "成功:".green().bold(), msg);
+    if !is_json() {
+        println!("{} {}", "成功:".green().bold(), msg);
+    }
 }
 
 /// 警告输出 (黄色)
 pub fn warn(msg: &str) {
-    println!("{} {}", "警告:".yellow().bold(), msg);
+    if !is_json() {
+        println!("{} {}", "警告:".yellow().bold(), msg);
+    }
 }
@@ -0,0 +1,277 @@
+//! 端点探测模块
+//!
+//! 向配置档案的 `ANTHROPIC_BASE_URL` 发送最小的认证请求，检查端点是否可达、凭据是否有效
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::config::{
+    API_KEY_KEY, AUTH_TOKEN_KEY, BASE_URL_KEY, CccConfig, expand_profile, resolve_env,
+};
+
+/// 未设置 `ANTHROPIC_BASE_URL` 时使用的官方地址
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+
+/// 请求头中的 API 版本
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// 指定模型的环境变量
+const MODEL_KEY: &str = "ANTHROPIC_MODEL";
+
+/// `/v1/messages` 探测请求未指定模型时使用的模型
+const DEFAULT_MODEL: &str = "claude-haiku-4-5";
+
+/// 探测结果分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// 请求成功
+    Ok,
+    /// 认证失败（HTTP 401 / 403）
+    AuthError,
+    /// 其他 HTTP 错误
+    HttpError,
+    /// 无法连接（DNS、连接、TLS 错误或超时）
+    Unreachable,
+}
+
+/// 端点探测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    /// 请求的 URL
+    pub url: String,
+    /// 结果分类
+    pub outcome: Outcome,
+    /// HTTP 状态码（无法连接时为 `None`）
+    pub status: Option<u16>,
+    /// 请求耗时（毫秒）
+    pub latency_ms: u128,
+    /// 错误信息
+    pub message: Option<String>,
+}
+
+impl ProbeResult {
+    /// 探测是否成功
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.outcome == Outcome::Ok
+    }
+
+    /// 失败原因，例如 `认证失败 (HTTP 401): invalid x-api-key`；探测成功时返回 `None`
+    #[must_use]
+    pub fn failure_reason(&self) -> Option<String> {
+        let kind = match (self.outcome, self.status) {
+            (Outcome::Ok, _) => return None,
+            (Outcome::AuthError, Some(status)) => format!("认证失败 (HTTP {})", status),
+            (Outcome::HttpError, Some(status)) => format!("请求失败 (HTTP {})", status),
+            _ => "无法连接".to_string(),
+        };
+        Some(match self.message.as_deref() {
+            Some(message) if !message.is_empty() => format!("{}: {}", kind, message),
+            _ => kind,
+        })
+    }
+}
+
+/// 解析配置档案（包括继承、占位符和 `apiKeyHelper`）并探测端点
+///
+/// # Errors
+///
+/// 返回配置档案不存在、继承存在循环、占位符解析失败、`apiKeyHelper` 运行失败等错误
+pub fn probe_profile(
+    config: &CccConfig,
+    name: &str,
+    timeout: Duration,
+) -> anyhow::Result<ProbeResult> {
+    let profile = expand_profile(name, &config.resolve_profile(name)?)?;
+    let vars = resolve_env(&profile)?;
+    Ok(probe(&vars, timeout))
+}
+
+/// 使用解析后的环境变量探测端点
+///
+/// 先请求 `GET /v1/models`；端点不支持该接口（404 / 405）时，改为发送 `max_tokens` 为 1 的
+/// `POST /v1/messages`。`ANTHROPIC_AUTH_TOKEN` 以 `Authorization: Bearer` 发送，
+/// `ANTHROPIC_API_KEY` 以 `x-api-key` 发送
+#[must_use]
+pub fn probe(vars: &BTreeMap<String, String>, timeout: Duration) -> ProbeResult {
+    let base_url = vars
+        .get(BASE_URL_KEY)
+        .map_or(DEFAULT_BASE_URL, String::as_str)
+        .trim_end_matches('/');
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+
+    let models = send(&agent, vars, &format!("{}/v1/models", base_url), None);
+    if !matches!(models.status, Some(404 | 405)) {
+        return models;
+    }
+
+    let model = vars.get(MODEL_KEY).map_or(DEFAULT_MODEL, String::as_str);
+    let body = serde_json::json!({
+        "model": model,
+        "max_tokens": 1,
+        "messages": [{ "role": "user", "content": "ping" }],
+    });
+    send(
+        &agent,
+        vars,
+        &format!("{}/v1/messages", base_url),
+        Some(&body.to_string()),
+    )
+}
+
+/// 发送单个请求，`body` 为 `None` 时发送 GET 请求
+fn send(
+    agent: &ureq::Agent,
+    vars: &BTreeMap<String, String>,
+    url: &str,
+    body: Option<&str>,
+) -> ProbeResult {
+    let mut headers = vec![("anthropic-version", ANTHROPIC_VERSION.to_string())];
+    headers.extend(auth_headers(vars));
+
+    let start = Instant::now();
+    let response = body.map_or_else(
+        || with_headers(agent.get(url), &headers).call(),
+        |payload| {
+            with_headers(agent.post(url).content_type("application/json"), &headers).send(payload)
+        },
+    );
+    let latency_ms = start.elapsed().as_millis();
+
+    match response {
+        Err(err) => ProbeResult {
+            url: url.to_string(),
+            outcome: Outcome::Unreachable,
+            status: None,
+            latency_ms,
+            message: Some(err.to_string()),
+        },
+        Ok(mut response) => {
+            let status = response.status().as_u16();
+            let outcome = match status {
+                200..=299 => Outcome::Ok,
+                401 | 403 => Outcome::AuthError,
+                _ => Outcome::HttpError,
+            };
+            let message = (outcome != Outcome::Ok).then(|| {
+                let text = response.body_mut().read_to_string().unwrap_or_default();
+                error_message(&text)
+            });
+            ProbeResult {
+                url: url.to_string(),
+                outcome,
+                status: Some(status),
+                latency_ms,
+                message,
+            }
+        }
+    }
+}
+
+/// 配置档案凭据对应的请求头
+///
+/// `ANTHROPIC_AUTH_TOKEN` 以 `Authorization: Bearer` 发送，`ANTHROPIC_API_KEY` 以 `x-api-key` 发送
+#[must_use]
+pub fn auth_headers(vars: &BTreeMap<String, String>) -> Vec<(&'static str, String)> {
+    let mut headers = Vec::new();
+    if let Some(token) = vars.get(AUTH_TOKEN_KEY) {
+        headers.push(("authorization", format!("Bearer {}", token)));
+    }
+    if let Some(key) = vars.get(API_KEY_KEY) {
+        headers.push(("x-api-key", key.clone()));
+    }
+    headers
+}
+
+/// 计算百分位数（最近秩法），`sorted` 需按升序排列，为空时返回 `None`
+#[must_use]
+pub fn percentile(sorted: &[u128], percent: usize) -> Option<u128> {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+/// 为请求添加请求头
+fn with_headers<B>(
+    mut request: ureq::RequestBuilder<B>,
+    headers: &[(&str, String)],
+) -> ureq::RequestBuilder<B> {
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+    request
+}
+
+/// 从错误响应中提取错误信息
+///
+/// 优先使用 Anthropic 错误格式中的 `error.message`，否则返回截断后的响应内容
+#[must_use]
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| {
+            value
+                .pointer("/error/message")
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.trim().chars().take(200).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_message() -> anyhow::Result<()> {
+        let body = r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+        if error_message(body) != "invalid x-api-key" {
+            anyhow::bail!("应提取 error.message");
+        }
+        if error_message("  Bad Gateway\n") != "Bad Gateway" {
+            anyhow::bail!("非 JSON 响应应返回原文");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_failure_reason() -> anyhow::Result<()> {
+        let mut result = ProbeResult {
+            url: "http://localhost/v1/models".to_string(),
+            outcome: Outcome::AuthError,
+            status: Some(401),
+            latency_ms: 3,
+            message: Some("invalid x-api-key".to_string()),
+        };
+        if result.failure_reason().as_deref() != Some("认证失败 (HTTP 401): invalid x-api-key")
+        {
+            anyhow::bail!("认证失败原因不匹配: {:?}", result.failure_reason());
+        }
+        result.outcome = Outcome::Ok;
+        result.status = Some(200);
+        if result.failure_reason().is_some() {
+            anyhow::bail!("探测成功时不应有失败原因");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_percentile() -> anyhow::Result<()> {
+        let sorted: Vec<u128> = (1..=20).map(|value| value * 10).collect();
+        if percentile(&sorted, 50) != Some(100) || percentile(&sorted, 95) != Some(190) {
+            anyhow::bail!("百分位数计算错误");
+        }
+        if percentile(&[42], 95) != Some(42) || percentile(&[], 50).is_some() {
+            anyhow::bail!("边界情况处理错误");
+        }
+        Ok(())
+    }
+}
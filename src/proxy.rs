@@ -0,0 +1,733 @@
+//! 本地路由代理模块
+//!
+//! 在本机监听 HTTP 请求，转发到代理目标配置档案的 `ANTHROPIC_BASE_URL` 并注入该配置的凭据。
+//! 每个请求都会重新读取代理目标，切换目标无需重启代理；响应体（包括 SSE 流）读取到多少就转发多少，
+//! 内容保持不变。`apiKeyHelper` 等命令生成的凭据只缓存一段时间，上游返回 401 时重新生成并重试一次
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use ureq::http::{StatusCode, Uri};
+
+use crate::{
+    config::{
+        BASE_URL_KEY, CccConfig, SkippedCandidate, expand_profile, paths::get_ccc_config_path,
+        resolve::uses_command, resolve_env,
+    },
+    probe::{DEFAULT_BASE_URL, auth_headers},
+};
+
+/// 默认监听端口
+pub const DEFAULT_PORT: u16 = 8787;
+
+/// 连接上游的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 请求行和单个请求头的最大长度（字节）
+const MAX_LINE_LEN: u64 = 8 * 1024;
+
+/// 请求头的最大数量
+const MAX_HEADERS: usize = 100;
+
+/// 请求体的最大长度（字节）
+const MAX_BODY_LEN: usize = 32 * 1024 * 1024;
+
+/// 由命令（`apiKeyHelper`、`${cmd:}`）生成的环境变量的缓存时间
+const COMMAND_VARS_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// 不转发给上游的请求头：逐跳请求头、由 ureq 重新生成的请求头，以及被替换的凭据
+const SKIPPED_REQUEST_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "expect",
+    "host",
+    "content-length",
+    "accept-encoding",
+    "authorization",
+    "x-api-key",
+];
+
+/// 不转发给客户端的响应头（响应体重新以 chunked 编码发送）
+const SKIPPED_RESPONSE_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+];
+
+/// 单个请求的转发记录
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestLog {
+    /// 请求方法
+    pub method: String,
+    /// 请求路径（包括查询参数）
+    pub path: String,
+    /// 处理请求的配置档案
+    pub profile: Option<String>,
+    /// 返回给客户端的 HTTP 状态码
+    pub status: Option<u16>,
+    /// 收到上游响应头的耗时（毫秒）
+    pub latency_ms: u128,
+    /// 因不可用被跳过的候选配置
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedCandidate>,
+    /// 错误信息
+    pub error: Option<String>,
+}
+
+/// 本地路由代理
+pub struct ProxyServer {
+    /// 监听的 socket
+    listener: TcpListener,
+    /// 请求转发逻辑，由各连接线程共享
+    router: Arc<Router>,
+}
+
+impl ProxyServer {
+    /// 在 `127.0.0.1` 的指定端口监听，`port` 为 0 时自动选择空闲端口
+    ///
+    /// # Errors
+    ///
+    /// 返回端口被占用等监听错误
+    pub fn bind(port: u16) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .with_context(|| format!("监听 127.0.0.1:{} 失败", port))?;
+        let local = listener.local_addr().context("获取监听地址失败")?;
+        Ok(Self {
+            listener,
+            router: Arc::new(Router::new(local)),
+        })
+    }
+
+    /// 实际监听的地址
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.router.local
+    }
+
+    /// 持续处理连接（每个连接一个线程），每个请求转发完成后调用 `log`
+    pub fn run(self, log: fn(&RequestLog)) {
+        for stream in self.listener.incoming().filter_map(Result::ok) {
+            let router = Arc::clone(&self.router);
+            std::thread::spawn(move || router.serve(stream, log));
+        }
+    }
+}
+
+/// 候选配置档案
+struct Target {
+    /// 配置名称
+    name: String,
+    /// 解析后的环境变量，解析失败时为失败原因
+    vars: Result<BTreeMap<String, String>, String>,
+    /// 环境变量是否来自缓存
+    cached: bool,
+}
+
+/// 缓存的环境变量
+#[derive(Clone)]
+struct CachedVars {
+    /// 解析后的环境变量
+    vars: BTreeMap<String, String>,
+    /// 解析时间
+    resolved_at: Instant,
+    /// 是否包含命令生成的值（只缓存 [`COMMAND_VARS_TTL`]）
+    from_command: bool,
+}
+
+impl CachedVars {
+    /// 缓存是否仍然有效
+    fn is_fresh(&self) -> bool {
+        !self.from_command || self.resolved_at.elapsed() < COMMAND_VARS_TTL
+    }
+}
+
+/// 解析后的环境变量缓存，避免每个请求都运行 `apiKeyHelper`
+#[derive(Default)]
+struct VarsCache {
+    /// 缓存对应的配置文件修改时间，配置文件被修改后缓存失效
+    modified: Option<SystemTime>,
+    /// 配置名称到环境变量的映射
+    vars: HashMap<String, CachedVars>,
+}
+
+/// 请求转发逻辑
+struct Router {
+    /// 代理监听的地址，用于检测转发到自身的循环
+    local: SocketAddr,
+    /// 上游 HTTP 客户端
+    agent: ureq::Agent,
+    /// 环境变量缓存
+    cache: Mutex<VarsCache>,
+}
+
+impl Router {
+    /// 创建转发逻辑
+    fn new(local: SocketAddr) -> Self {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_connect(Some(CONNECT_TIMEOUT))
+            .http_status_as_error(false)
+            .max_redirects(0)
+            .build()
+            .into();
+        Self {
+            local,
+            agent,
+            cache: Mutex::new(VarsCache::default()),
+        }
+    }
+
+    /// 处理单个连接上的所有请求（支持 keep-alive）
+    fn serve(&self, stream: TcpStream, log: fn(&RequestLog)) {
+        let Ok(read_half) = stream.try_clone() else {
+            return;
+        };
+        let mut reader = BufReader::new(read_half);
+        let mut client = stream;
+        loop {
+            let request = match read_request(&mut reader) {
+                Ok(Some(request)) => request,
+                Ok(None) => return,
+                Err(err) => {
+                    let _ = write_error(&mut client, 400, &format!("{:#}", err));
+                    return;
+                }
+            };
+            // 只接受发往本机地址的请求，防止网页通过 DNS rebinding 借用代理注入的凭据
+            if !is_local_host(request.header("host"), self.local.port()) {
+                let _ = write_error(&mut client, 403, "Host 请求头必须是本机地址");
+                return;
+            }
+            let (entry, reusable) = self.forward(&request, &mut client);
+            log(&entry);
+            if !reusable {
+                return;
+            }
+        }
+    }
+
+    /// 依次尝试代理目标及其备用配置，将第一个可用的响应转发给客户端
+    ///
+    /// 无法连接、HTTP 429 和 5xx 视为不可用（最后一个候选的响应总是原样转发）。
+    /// 返回转发记录以及连接能否继续使用
+    fn forward(&self, request: &Request, client: &mut TcpStream) -> (RequestLog, bool) {
+        let start = Instant::now();
+        let mut log = RequestLog {
+            method: request.method.clone(),
+            path: request.target.clone(),
+            profile: None,
+            status: None,
+            latency_ms: 0,
+            skipped: Vec::new(),
+            error: None,
+        };
+
+        let targets = match self.targets() {
+            Ok(targets) => targets,
+            Err(err) => {
+                let message = format!("{:#}", err);
+                log.status = Some(503);
+                let reusable = write_error(client, 503, &message).is_ok() && request.keep_alive();
+                log.error = Some(message);
+                return (log, reusable);
+            }
+        };
+
+        let count = targets.len();
+        for (index, target) in targets.into_iter().enumerate() {
+            let vars = match target.vars {
+                Ok(vars) => vars,
+                Err(reason) => {
+                    log.skipped.push(SkippedCandidate {
+                        profile: target.name,
+                        reason,
+                    });
+                    continue;
+                }
+            };
+            let base_url = vars
+                .get(BASE_URL_KEY)
+                .map_or(DEFAULT_BASE_URL, String::as_str)
+                .trim_end_matches('/');
+            if self.is_self(base_url) {
+                log.skipped.push(SkippedCandidate {
+                    profile: target.name,
+                    reason: format!("{} 指向代理自身", BASE_URL_KEY),
+                });
+                continue;
+            }
+
+            let url = format!("{}{}", base_url, request.target);
+            let mut sent = self.send(request, &url, &vars);
+            // 缓存的凭据可能已过期：重新解析（重新运行 apiKeyHelper）后重试一次
+            if target.cached
+                && sent
+                    .as_ref()
+                    .is_ok_and(|response| response.status() == StatusCode::UNAUTHORIZED)
+                && let Ok(refreshed) = self.refresh(&target.name)
+            {
+                sent = self.send(request, &url, &refreshed);
+            }
+            let response = match sent {
+                Ok(response) => response,
+                Err(err) => {
+                    log.skipped.push(SkippedCandidate {
+                        profile: target.name,
+                        reason: format!("无法连接: {}", err),
+                    });
+                    continue;
+                }
+            };
+            let status = response.status().as_u16();
+            if index + 1 < count && (status == 429 || status >= 500) {
+                log.skipped.push(SkippedCandidate {
+                    profile: target.name,
+                    reason: format!("HTTP {}", status),
+                });
+                continue;
+            }
+
+            log.profile = Some(target.name);
+            log.status = Some(status);
+            log.latency_ms = start.elapsed().as_millis();
+            return match write_response(client, request, response) {
+                Ok(()) => (log, request.keep_alive()),
+                Err(err) => {
+                    log.error = Some(format!("转发响应失败: {}", err));
+                    (log, false)
+                }
+            };
+        }
+
+        let reasons: Vec<_> = log
+            .skipped
+            .iter()
+            .map(|skipped| format!("{} ({})", skipped.profile, skipped.reason))
+            .collect();
+        let message = format!("没有可用的配置档案: {}", reasons.join("; "));
+        log.status = Some(502);
+        log.latency_ms = start.elapsed().as_millis();
+        let reusable = write_error(client, 502, &message).is_ok() && request.keep_alive();
+        log.error = Some(message);
+        (log, reusable)
+    }
+
+    /// 读取代理目标（未设置时使用当前活跃配置）及其备用配置，并解析各自的环境变量
+    fn targets(&self) -> anyhow::Result<Vec<Target>> {
+        let modified = get_ccc_config_path()
+            .ok()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .and_then(|metadata| metadata.modified().ok());
+        let config = CccConfig::load().context("加载配置失败")?;
+        let target = config
+            .proxy_target
+            .as_deref()
+            .or(config.current.as_deref())
+            .context("未设置代理目标，请运行 cccrs proxy switch <name>")?;
+        let names = config.fallback_candidates(target)?;
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if cache.modified != modified {
+            *cache = VarsCache {
+                modified,
+                vars: HashMap::new(),
+            };
+        }
+        let mut targets = Vec::with_capacity(names.len());
+        for name in names {
+            let cached = cache
+                .vars
+                .get(name)
+                .filter(|cached| cached.is_fresh())
+                .map(|cached| cached.vars.clone());
+            let resolved = cached.map_or_else(
+                || Target {
+                    name: name.to_string(),
+                    vars: resolve_into(&mut cache, &config, name),
+                    cached: false,
+                },
+                |vars| Target {
+                    name: name.to_string(),
+                    vars: Ok(vars),
+                    cached: true,
+                },
+            );
+            targets.push(resolved);
+        }
+        Ok(targets)
+    }
+
+    /// 重新解析配置档案的环境变量并更新缓存
+    fn refresh(&self, name: &str) -> Result<BTreeMap<String, String>, String> {
+        let config = CccConfig::load().map_err(|err| format!("加载配置失败: {:#}", err))?;
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        resolve_into(&mut cache, &config, name)
+    }
+
+    /// 判断上游地址是否指向代理自身
+    fn is_self(&self, base_url: &str) -> bool {
+        let Ok(uri) = base_url.parse::<Uri>() else {
+            return false;
+        };
+        let host = uri.host().unwrap_or_default().trim_matches(['[', ']']);
+        let is_local_host = host.eq_ignore_ascii_case("localhost")
+            || host
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback() || ip == self.local.ip());
+        is_local_host && uri.port_u16() == Some(self.local.port())
+    }
+
+    /// 向上游发送请求，替换凭据请求头
+    fn send(
+        &self,
+        request: &Request,
+        url: &str,
+        vars: &BTreeMap<String, String>,
+    ) -> Result<ureq::http::Response<ureq::Body>, ureq::Error> {
+        let mut builder = ureq::http::Request::builder()
+            .method(request.method.as_str())
+            .uri(url);
+        for (name, value) in &request.headers {
+            if !SKIPPED_REQUEST_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+        }
+        for (name, value) in auth_headers(vars) {
+            builder = builder.header(name, value);
+        }
+        if request.body.is_empty() && !matches!(request.method.as_str(), "POST" | "PUT" | "PATCH") {
+            self.agent.run(builder.body(())?)
+        } else {
+            self.agent.run(builder.body(request.body.as_slice())?)
+        }
+    }
+}
+
+/// 解析配置档案（包括继承、占位符和 `apiKeyHelper`）的环境变量并写入缓存，失败时返回失败原因
+fn resolve_into(
+    cache: &mut VarsCache,
+    config: &CccConfig,
+    name: &str,
+) -> Result<BTreeMap<String, String>, String> {
+    let profile = config
+        .resolve_profile(name)
+        .map_err(|err| format!("配置错误: {:#}", err))?;
+    let vars = expand_profile(name, &profile)
+        .and_then(|expanded| resolve_env(&expanded))
+        .map_err(|err| format!("配置错误: {:#}", err))?;
+    cache.vars.insert(
+        name.to_string(),
+        CachedVars {
+            vars: vars.clone(),
+            resolved_at: Instant::now(),
+            from_command: uses_command(&profile),
+        },
+    );
+    Ok(vars)
+}
+
+/// 客户端请求
+struct Request {
+    /// 请求方法
+    method: String,
+    /// 请求路径（包括查询参数）
+    target: String,
+    /// 是否为 HTTP/1.0 请求
+    http10: bool,
+    /// 请求头
+    headers: Vec<(String, String)>,
+    /// 请求体
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// 获取请求头（不区分大小写）
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 响应后是否保持连接
+    fn keep_alive(&self) -> bool {
+        let connection = self.header("connection").map(str::to_ascii_lowercase);
+        if self.http10 {
+            connection.as_deref() == Some("keep-alive")
+        } else {
+            connection.as_deref() != Some("close")
+        }
+    }
+}
+
+/// 读取一个 HTTP/1.x 请求，连接已关闭时返回 `None`
+fn read_request(reader: &mut impl BufRead) -> anyhow::Result<Option<Request>> {
+    let mut line = String::new();
+    if read_line_limited(reader, &mut line).context("读取请求失败")? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        anyhow::bail!("无效的请求行: {}", line.trim());
+    };
+    if !target.starts_with('/') {
+        anyhow::bail!("只支持转发 Anthropic API 请求，不能作为通用 HTTP 代理使用");
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        let mut header = String::new();
+        if read_line_limited(reader, &mut header).context("读取请求头失败")? == 0 {
+            anyhow::bail!("请求头不完整");
+        }
+        let trimmed = header.trim_end();
+        if trimmed.is_empty() {
+            break;
+        }
+        let (name, value) = trimmed
+            .split_once(':')
+            .with_context(|| format!("无效的请求头: {}", trimmed))?;
+        if headers.len() >= MAX_HEADERS {
+            anyhow::bail!("请求头数量超过 {} 个", MAX_HEADERS);
+        }
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut request = Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        http10: version == "HTTP/1.0",
+        headers,
+        body: Vec::new(),
+    };
+    if request
+        .header("transfer-encoding")
+        .is_some_and(|encoding| encoding.to_ascii_lowercase().contains("chunked"))
+    {
+        request.body = read_chunked(reader)?;
+    } else if let Some(length) = request.header("content-length") {
+        let length: usize = length.parse().context("无效的 Content-Length")?;
+        if length > MAX_BODY_LEN {
+            anyhow::bail!("请求体超过 {} 字节", MAX_BODY_LEN);
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).context("读取请求体失败")?;
+        request.body = body;
+    }
+    Ok(Some(request))
+}
+
+/// 读取一行（包括换行符），超过 [`MAX_LINE_LEN`] 时返回错误
+fn read_line_limited(reader: &mut impl BufRead, line: &mut String) -> anyhow::Result<usize> {
+    let read = reader.take(MAX_LINE_LEN).read_line(line)?;
+    if read as u64 == MAX_LINE_LEN && !line.ends_with('\n') {
+        anyhow::bail!("请求行或请求头超过 {} 字节", MAX_LINE_LEN);
+    }
+    Ok(read)
+}
+
+/// Host 请求头是否为代理监听的本机地址（`127.0.0.1`、`localhost` 或 `[::1]`）
+fn is_local_host(host: Option<&str>, port: u16) -> bool {
+    host.is_some_and(|host| {
+        ["127.0.0.1", "localhost", "[::1]"]
+            .iter()
+            .any(|name| host.eq_ignore_ascii_case(&format!("{}:{}", name, port)))
+    })
+}
+
+/// 读取 chunked 编码的请求体
+fn read_chunked(reader: &mut impl BufRead) -> anyhow::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size_line = String::new();
+        read_line_limited(reader, &mut size_line).context("读取请求体失败")?;
+        let size_text = size_line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size_text, 16).context("无效的 chunk 长度")?;
+        if size == 0 {
+            // 跳过 trailer
+            loop {
+                let mut trailer = String::new();
+                if read_line_limited(reader, &mut trailer).context("读取请求体失败")? == 0
+                    || trailer.trim().is_empty()
+                {
+                    return Ok(body);
+                }
+            }
+        }
+        if size > MAX_BODY_LEN.saturating_sub(body.len()) {
+            anyhow::bail!("请求体超过 {} 字节", MAX_BODY_LEN);
+        }
+        let mut chunk = vec![0; size];
+        reader.read_exact(&mut chunk).context("读取请求体失败")?;
+        body.extend_from_slice(&chunk);
+        let mut crlf = [0u8; 2];
+        reader.read_exact(&mut crlf).context("读取请求体失败")?;
+    }
+}
+
+/// 将上游响应转发给客户端，响应体以 chunked 编码逐块发送
+fn write_response(
+    client: &mut TcpStream,
+    request: &Request,
+    response: ureq::http::Response<ureq::Body>,
+) -> std::io::Result<()> {
+    let (parts, body) = response.into_parts();
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        parts.status.as_u16(),
+        parts.status.canonical_reason().unwrap_or_default()
+    )
+    .into_bytes();
+    for (name, value) in &parts.headers {
+        if SKIPPED_RESPONSE_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    let has_body = request.method != "HEAD"
+        && !parts.status.is_informational()
+        && !matches!(parts.status.as_u16(), 204 | 304);
+    if has_body {
+        head.extend_from_slice(b"transfer-encoding: chunked\r\n");
+    }
+    if !request.keep_alive() {
+        head.extend_from_slice(b"connection: close\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+    client.write_all(&head)?;
+    client.flush()?;
+
+    if has_body {
+        copy_chunked(&mut body.into_reader(), client)?;
+    }
+    Ok(())
+}
+
+/// 以 chunked 编码复制数据，每读取到一块数据立即发送，保证 SSE 事件不被缓冲
+fn copy_chunked(reader: &mut impl Read, writer: &mut impl Write) -> std::io::Result<()> {
+    let mut buffer = vec![0u8; 16 * 1024];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        write!(writer, "{:x}\r\n", read)?;
+        writer.write_all(buffer.get(..read).unwrap_or_default())?;
+        writer.write_all(b"\r\n")?;
+        writer.flush()?;
+    }
+    writer.write_all(b"0\r\n\r\n")?;
+    writer.flush()
+}
+
+/// 以 Anthropic 错误格式返回代理自身的错误
+fn write_error(client: &mut TcpStream, status: u16, message: &str) -> std::io::Result<()> {
+    let body = serde_json::json!({
+        "type": "error",
+        "error": { "type": "api_error", "message": format!("cccrs proxy: {}", message) },
+    })
+    .to_string();
+    let reason = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default();
+    write!(
+        client,
+        "HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    client.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() -> anyhow::Result<()> {
+        let raw = "POST /v1/messages?beta=true HTTP/1.1\r\nHost: localhost:8787\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\n\r\nGET /v1/models HTTP/1.1\r\n\r\n";
+        let mut reader = BufReader::new(raw.as_bytes());
+        let request = read_request(&mut reader)?.context("应读取到请求")?;
+        if request.method != "POST"
+            || request.target != "/v1/messages?beta=true"
+            || request.body != b"hello world"
+            || request.keep_alive()
+        {
+            anyhow::bail!("请求解析结果不匹配");
+        }
+        let next = read_request(&mut reader)?.context("应读取到第二个请求")?;
+        if next.target != "/v1/models" || !next.body.is_empty() || !next.keep_alive() {
+            anyhow::bail!("第二个请求解析结果不匹配");
+        }
+        if read_request(&mut reader)?.is_some() {
+            anyhow::bail!("连接关闭时应返回 None");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_request_limits() {
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(10 * 1024));
+        let many_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X-A: b\r\n".repeat(101));
+        let large_body = "POST / HTTP/1.1\r\nContent-Length: 1099511627776\r\n\r\n";
+        let large_chunk = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nffffffffff\r\n";
+        for raw in [
+            long_line.as_str(),
+            many_headers.as_str(),
+            large_body,
+            large_chunk,
+        ] {
+            assert!(read_request(&mut BufReader::new(raw.as_bytes())).is_err());
+        }
+    }
+
+    #[test]
+    fn test_is_local_host() {
+        assert!(is_local_host(Some("127.0.0.1:8787"), 8787));
+        assert!(is_local_host(Some("LOCALHOST:8787"), 8787));
+        assert!(is_local_host(Some("[::1]:8787"), 8787));
+        assert!(!is_local_host(Some("evil.example.com:8787"), 8787));
+        assert!(!is_local_host(Some("localhost:8788"), 8787));
+        assert!(!is_local_host(Some("localhost"), 8787));
+        assert!(!is_local_host(None, 8787));
+    }
+
+    #[test]
+    fn test_copy_chunked() -> anyhow::Result<()> {
+        let mut output = Vec::new();
+        copy_chunked(&mut &b"event: ping\n\n"[..], &mut output)?;
+        if output != b"d\r\nevent: ping\n\n\r\n0\r\n\r\n" {
+            anyhow::bail!(
+                "chunked 编码结果不匹配: {:?}",
+                String::from_utf8_lossy(&output)
+            );
+        }
+        Ok(())
+    }
+}
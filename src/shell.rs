@@ -0,0 +1,156 @@
+//! Shell 模块
+//!
+//! 生成不同 shell 下设置和清除环境变量的语句
+
+use std::fmt;
+
+use crate::{config::EnvConfig, error::CccError};
+
+/// 支持的 shell 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    /// Bash
+    Bash,
+    /// Zsh
+    Zsh,
+    /// Fish
+    Fish,
+    /// `PowerShell`
+    Powershell,
+}
+
+impl Shell {
+    /// 根据当前环境检测 shell 类型
+    ///
+    /// Unix 平台读取 `SHELL` 环境变量，无法识别时使用 Bash；Windows 平台使用 `PowerShell`
+    #[must_use]
+    pub fn detect() -> Self {
+        if cfg!(windows) {
+            return Self::Powershell;
+        }
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        match shell.rsplit('/').next().unwrap_or_default() {
+            "zsh" => Self::Zsh,
+            "fish" => Self::Fish,
+            "pwsh" | "powershell" => Self::Powershell,
+            _ => Self::Bash,
+        }
+    }
+
+    /// 生成设置环境变量的语句
+    ///
+    /// # Errors
+    ///
+    /// 变量名无效时返回错误（变量名不加引号，无效的变量名可能被 shell 当作命令执行）
+    pub fn export(self, key: &str, value: &str) -> Result<String, CccError> {
+        let key = checked_key(key)?;
+        Ok(match self {
+            Self::Bash | Self::Zsh => format!("export {}={}", key, self.quote(value)),
+            Self::Fish => format!("set -gx {} {}", key, self.quote(value)),
+            Self::Powershell => format!("$env:{} = {}", key, self.quote(value)),
+        })
+    }
+
+    /// 生成清除环境变量的语句
+    ///
+    /// # Errors
+    ///
+    /// 变量名无效时返回错误
+    pub fn unset(self, key: &str) -> Result<String, CccError> {
+        let key = checked_key(key)?;
+        Ok(match self {
+            Self::Bash | Self::Zsh => format!("unset {}", key),
+            Self::Fish => format!("set -e {}", key),
+            Self::Powershell => {
+                format!("Remove-Item Env:{} -ErrorAction SilentlyContinue", key)
+            }
+        })
+    }
+
+    /// 按 shell 规则为值加上单引号
+    #[must_use]
+    pub fn quote(self, value: &str) -> String {
+        let escaped = match self {
+            Self::Bash | Self::Zsh => value.replace('\'', r"'\''"),
+            Self::Fish => value.replace('\\', r"\\").replace('\'', r"\'"),
+            Self::Powershell => value.replace('\'', "''"),
+        };
+        format!("'{}'", escaped)
+    }
+}
+
+/// 校验环境变量名
+fn checked_key(key: &str) -> Result<&str, CccError> {
+    if EnvConfig::is_valid_key(key) {
+        Ok(key)
+    } else {
+        Err(CccError::InvalidEnvKey(key.to_string()))
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+            Self::Powershell => "powershell",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_quoting() {
+        assert_eq!(
+            Shell::Bash
+                .export("ANTHROPIC_AUTH_TOKEN", "sk-it's")
+                .ok()
+                .as_deref(),
+            Some(r"export ANTHROPIC_AUTH_TOKEN='sk-it'\''s'")
+        );
+        assert_eq!(
+            Shell::Fish
+                .export("ANTHROPIC_AUTH_TOKEN", r"sk-it's\x")
+                .ok()
+                .as_deref(),
+            Some(r"set -gx ANTHROPIC_AUTH_TOKEN 'sk-it\'s\\x'")
+        );
+        assert_eq!(
+            Shell::Powershell
+                .export("ANTHROPIC_AUTH_TOKEN", "sk-it's")
+                .ok()
+                .as_deref(),
+            Some("$env:ANTHROPIC_AUTH_TOKEN = 'sk-it''s'")
+        );
+    }
+
+    #[test]
+    fn test_unset() {
+        assert_eq!(
+            Shell::Zsh.unset("ANTHROPIC_MODEL").ok().as_deref(),
+            Some("unset ANTHROPIC_MODEL")
+        );
+        assert_eq!(
+            Shell::Fish.unset("ANTHROPIC_MODEL").ok().as_deref(),
+            Some("set -e ANTHROPIC_MODEL")
+        );
+        assert_eq!(
+            Shell::Powershell.unset("ANTHROPIC_MODEL").ok().as_deref(),
+            Some("Remove-Item Env:ANTHROPIC_MODEL -ErrorAction SilentlyContinue")
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_key() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::Powershell] {
+            assert!(shell.export("X;touch /tmp/pwned;Y", "v").is_err());
+            assert!(shell.export("$(id)", "v").is_err());
+            assert!(shell.unset("X Y").is_err());
+        }
+    }
+}
@@ -32,6 +32,12 @@ fn setup_temp_home() -> Result<(TempDir, PathBuf, PathBuf)> {
         std::env::set_var("USERPROFILE", temp_home.to_string_lossy().as_ref());
     }
 
+    // 清除会覆盖配置路径的环境变量
+    unsafe {
+        std::env::remove_var("CLAUDE_CONFIG_DIR");
+        std::env::remove_var("CCCRS_CONFIG");
+    }
+
     Ok((temp_dir, settings_path, ccc_config_path))
 }
 
@@ -241,9 +247,14 @@ fn test_use_command_updates_settings() -> Result<()> {
         anyhow::bail!("其他字段不匹配");
     }
 
-    // 验证备份文件已创建
-    use claude_code_config_rs::config::paths::CLAUDE_DIR;
-    let backups: Vec<_> = std::fs::read_dir(temp_dir.path().join("test_home").join(CLAUDE_DIR))
+    // 验证备份文件已创建在独立的备份目录中
+    use claude_code_config_rs::config::paths::{BACKUP_DIR, CLAUDE_DIR};
+    let backup_dir = temp_dir
+        .path()
+        .join("test_home")
+        .join(CLAUDE_DIR)
+        .join(BACKUP_DIR);
+    let backups: Vec<_> = std::fs::read_dir(backup_dir)
         .context("读取备份目录失败")?
         .filter_map(std::result::Result::ok)
         .map(|e| e.path())
@@ -392,6 +403,114 @@ fn test_add_invalid_name() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[serial]
+fn test_add_with_flags() -> Result<()> {
+    let (_temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("add")
+        .arg("kimi")
+        .arg("--base-url")
+        .arg("https://api.moonshot.cn/anthropic")
+        .arg("--api-key")
+        .arg("sk-kimi")
+        .arg("--env")
+        .arg("ANTHROPIC_MODEL=kimi-k2")
+        .assert()
+        .success();
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("add")
+        .arg("piped")
+        .arg("--base-url")
+        .arg("https://api.piped.com")
+        .arg("--api-key-stdin")
+        .write_stdin("sk-from-stdin\n")
+        .assert()
+        .success();
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?)
+            .context("解析配置失败")?;
+    let kimi_env = saved
+        .pointer("/profiles/kimi/env")
+        .context("缺少 kimi 配置")?;
+    if *kimi_env
+        != serde_json::json!({
+            "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+            "ANTHROPIC_AUTH_TOKEN": "sk-kimi",
+            "ANTHROPIC_MODEL": "kimi-k2"
+        })
+    {
+        anyhow::bail!("kimi 配置不匹配: {}", kimi_env);
+    }
+    let piped_key = saved.pointer("/profiles/piped/env/ANTHROPIC_AUTH_TOKEN");
+    if piped_key != Some(&serde_json::json!("sk-from-stdin")) {
+        anyhow::bail!("从 stdin 读取的 API Key 不匹配: {:?}", piped_key);
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_add_fails_without_tty_when_data_missing() -> Result<()> {
+    let (_temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("add")
+        .arg("partial")
+        .arg("--base-url")
+        .arg("https://api.partial.com")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("缺少 API Key"))
+        .stderr(predicate::str::contains("stdin 不是终端"));
+
+    // 使用 --api-key-stdin 时说明无法交互的真正原因
+    let mut stdin_cmd = Command::new(cargo_bin!("cccrs"));
+    stdin_cmd
+        .args(["add", "partial", "--api-key-stdin"])
+        .write_stdin("sk-from-stdin\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("缺少 Base URL"))
+        .stderr(predicate::str::contains("已使用 --api-key-stdin"));
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("add")
+        .arg("bad-env")
+        .arg("--base-url")
+        .arg("https://api.partial.com")
+        .arg("--api-key")
+        .arg("sk-test")
+        .arg("--env")
+        .arg("NOT VALID")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("KEY=VALUE"));
+
+    // 指定 API Key Helper 时可省略 API Key
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("add")
+        .arg("helper")
+        .arg("--base-url")
+        .arg("https://api.helper.com")
+        .arg("--helper")
+        .arg("echo sk-helper")
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?;
+    if content.contains("partial") || content.contains("bad-env") {
+        anyhow::bail!("失败的添加不应写入配置");
+    }
+    if !content.contains("echo sk-helper") {
+        anyhow::bail!("API Key Helper 未保存");
+    }
+    Ok(())
+}
+
 #[test]
 #[serial]
 fn test_remove_command_del_alias() -> Result<()> {
@@ -471,15 +590,14 @@ fn test_save_and_load_integration() -> Result<()> {
 #[test]
 #[serial]
 fn test_backup_integration() -> Result<()> {
-    use claude_code_config_rs::config::claude_settings::ClaudeSettings;
+    use claude_code_config_rs::config::backup::backup_settings;
 
     let (_temp_dir, settings_path, _ccc_config_path) = setup_temp_home()?;
 
     // 创建初始 settings.json
     create_initial_settings(&settings_path)?;
 
-    let settings = ClaudeSettings::default();
-    let backup_path = settings.backup().context("备份失败")?;
+    let backup_path = backup_settings(None).context("备份失败")?;
 
     if !backup_path.exists() {
         anyhow::bail!("备份文件不存在");
@@ -494,3 +612,2472 @@ fn test_backup_integration() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[serial]
+fn test_use_applies_extra_env_vars() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi",
+                    "ANTHROPIC_MODEL": "kimi-k2",
+                    "API_TIMEOUT_MS": "600000"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("use").arg("kimi").assert().success();
+
+    let settings_content =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+    let settings: serde_json::Value =
+        serde_json::from_str(&settings_content).context("解析 JSON 失败")?;
+    let env = settings
+        .get("env")
+        .and_then(|e| e.as_object())
+        .context("获取 env 失败")?;
+
+    if env.get("ANTHROPIC_MODEL").and_then(|v| v.as_str()) != Some("kimi-k2") {
+        anyhow::bail!("ANTHROPIC_MODEL 未写入");
+    }
+    if env.get("API_TIMEOUT_MS").and_then(|v| v.as_str()) != Some("600000") {
+        anyhow::bail!("API_TIMEOUT_MS 未写入");
+    }
+
+    // list 应该能通过完整环境变量检测到当前配置
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("当前活跃配置: kimi"))
+        .stdout(predicate::str::contains("ANTHROPIC_MODEL = kimi-k2"));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_import_keeps_extra_env_vars() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    std::fs::write(
+        &settings_path,
+        r#"{
+    "env": {
+        "ANTHROPIC_BASE_URL": "https://api.anthropic.com",
+        "ANTHROPIC_AUTH_TOKEN": "sk-test-key",
+        "ANTHROPIC_SMALL_FAST_MODEL": "claude-haiku",
+        "API_TIMEOUT_MS": 600000
+    }
+}"#,
+    )
+    .context("写入初始 settings 失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("import").arg("full").assert().success();
+
+    let config_content = std::fs::read_to_string(&ccc_config_path).context("读取配置文件失败")?;
+    let config: serde_json::Value =
+        serde_json::from_str(&config_content).context("解析 JSON 失败")?;
+    let env = config
+        .pointer("/profiles/full/env")
+        .and_then(|e| e.as_object())
+        .context("获取 env 失败")?;
+
+    if env
+        .get("ANTHROPIC_SMALL_FAST_MODEL")
+        .and_then(|v| v.as_str())
+        != Some("claude-haiku")
+    {
+        anyhow::bail!("ANTHROPIC_SMALL_FAST_MODEL 未导入");
+    }
+    if env.get("API_TIMEOUT_MS").and_then(|v| v.as_str()) != Some("600000") {
+        anyhow::bail!("API_TIMEOUT_MS 未导入");
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_use_clean_removes_stale_keys() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    std::fs::write(
+        &settings_path,
+        r#"{
+    "otherField": "should remain",
+    "env": {
+        "DISABLE_TELEMETRY": "1"
+    }
+}"#,
+    )
+    .context("写入初始 settings 失败")?;
+
+    let config = r#"{
+        "profiles": {
+            "helper": {
+                "api_key_helper": "echo sk-helper",
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.helper.com",
+                    "ANTHROPIC_MODEL": "helper-model"
+                }
+            },
+            "plain": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.plain.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-plain"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("use").arg("helper").assert().success();
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("use")
+        .arg("plain")
+        .arg("--clean")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("已移除过期字段"));
+
+    let settings_content =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+    let settings: serde_json::Value =
+        serde_json::from_str(&settings_content).context("解析 JSON 失败")?;
+
+    if settings.get("apiKeyHelper").is_some() {
+        anyhow::bail!("apiKeyHelper 应已被移除");
+    }
+    if settings.pointer("/env/ANTHROPIC_MODEL").is_some() {
+        anyhow::bail!("ANTHROPIC_MODEL 应已被移除");
+    }
+    if settings
+        .pointer("/env/ANTHROPIC_BASE_URL")
+        .and_then(|v| v.as_str())
+        != Some("https://api.plain.com")
+    {
+        anyhow::bail!("Base URL 不匹配");
+    }
+    if settings
+        .pointer("/env/DISABLE_TELEMETRY")
+        .and_then(|v| v.as_str())
+        != Some("1")
+    {
+        anyhow::bail!("用户自行添加的环境变量应保留");
+    }
+    if settings.get("otherField").and_then(|v| v.as_str()) != Some("should remain") {
+        anyhow::bail!("其他字段不匹配");
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_use_without_clean_keeps_previous_keys() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "helper": {
+                "api_key_helper": "echo sk-helper",
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.helper.com"
+                }
+            },
+            "plain": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.plain.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-plain"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("use").arg("helper").assert().success();
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("use").arg("plain").assert().success();
+
+    let settings_content =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+    let settings: serde_json::Value =
+        serde_json::from_str(&settings_content).context("解析 JSON 失败")?;
+    if settings.get("apiKeyHelper").and_then(|v| v.as_str()) != Some("echo sk-helper") {
+        anyhow::bail!("未使用 --clean 时 apiKeyHelper 应保留");
+    }
+
+    // 记录的管理字段应包含 apiKeyHelper，以便之后使用 --clean 清理
+    let config_content = std::fs::read_to_string(&ccc_config_path).context("读取配置文件失败")?;
+    let saved: serde_json::Value =
+        serde_json::from_str(&config_content).context("解析 JSON 失败")?;
+    if saved
+        .pointer("/managed/api_key_helper")
+        .and_then(serde_json::Value::as_bool)
+        != Some(true)
+    {
+        anyhow::bail!("管理字段记录不匹配");
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_use_fails_when_config_locked() -> Result<()> {
+    use claude_code_config_rs::config::ConfigLock;
+
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "locked": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.locked.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-locked"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    // 持有配置锁，模拟另一个进程正在修改配置
+    let lock = ConfigLock::acquire().context("获取配置锁失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.env("CCCRS_LOCK_TIMEOUT", "0.2")
+        .arg("use")
+        .arg("locked")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("等待配置锁超时"));
+
+    // 释放锁后可以正常切换
+    drop(lock);
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("use").arg("locked").assert().success();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_backup_list_and_restore() -> Result<()> {
+    use claude_code_config_rs::config::paths::BACKUP_DIR;
+
+    let (_temp_dir, settings_path, _ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+    let backup_path = settings_path.with_file_name("settings.json.backup.20260101_120000");
+    std::fs::write(
+        &backup_path,
+        r#"{"env": {"ANTHROPIC_BASE_URL": "https://api.restored.com"}}"#,
+    )
+    .context("写入备份失败")?;
+
+    // 备份目录中已有同名备份时，旧位置的文件不会重复列出
+    let backup_dir = settings_path.with_file_name(BACKUP_DIR);
+    std::fs::create_dir_all(&backup_dir).context("创建备份目录失败")?;
+    std::fs::write(
+        backup_dir.join("settings.json.backup.20260101_110000"),
+        "{}",
+    )
+    .context("写入备份失败")?;
+    std::fs::write(
+        settings_path.with_file_name("settings.json.backup.20260101_110000"),
+        "{}",
+    )
+    .context("写入备份失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("backup")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("20260101_120000"))
+        .stdout(predicate::str::contains("共 2 个备份"));
+
+    // 列出备份不移动文件
+    if !backup_path.exists() {
+        anyhow::bail!("列出备份不应移动旧版本的备份");
+    }
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("backup")
+        .arg("diff")
+        .arg("20260101_120000")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("/env/ANTHROPIC_BASE_URL"))
+        .stdout(predicate::str::contains("/otherField"))
+        .stdout(predicate::str::contains("sk-test-key").not());
+
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("backup")
+        .arg("restore")
+        .arg("latest")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("已备份当前配置"));
+
+    let settings_content =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+    if !settings_content.contains("https://api.restored.com") {
+        anyhow::bail!("settings.json 未恢复");
+    }
+
+    // 备份时旧版本的备份被移动到备份目录，同名冲突的文件保留在原位置
+    if backup_path.exists()
+        || !backup_dir
+            .join("settings.json.backup.20260101_120000")
+            .exists()
+    {
+        anyhow::bail!("旧版本的备份应被移动到备份目录");
+    }
+
+    // 恢复前应先备份当前文件，因此此时共有三个备份
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.arg("backup")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("共 3 个备份"));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_backup_restore_keeps_restored_backup() -> Result<()> {
+    use claude_code_config_rs::config::paths::BACKUP_DIR;
+
+    let (_temp_dir, settings_path, _ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+    let backup_dir = settings_path.with_file_name(BACKUP_DIR);
+    std::fs::create_dir_all(&backup_dir).context("创建备份目录失败")?;
+    let restored = backup_dir.join("settings.json.backup.20260101_000000");
+    std::fs::write(
+        &restored,
+        r#"{"env": {"ANTHROPIC_BASE_URL": "https://api.restored.com"}}"#,
+    )
+    .context("写入备份失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.args(["backup", "retention", "--keep", "1"])
+        .assert()
+        .success();
+
+    // 恢复前的备份按保留策略清理时，不会删除正在恢复的备份
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.args(["backup", "restore", "20260101_000000"])
+        .assert()
+        .success();
+    if !restored.exists() {
+        anyhow::bail!("正在恢复的备份不应被清理");
+    }
+    let settings_content =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+    if !settings_content.contains("https://api.restored.com") {
+        anyhow::bail!("settings.json 未恢复");
+    }
+    let remaining = std::fs::read_dir(&backup_dir)
+        .context("读取备份目录失败")?
+        .count();
+    if remaining != 2 {
+        anyhow::bail!("应保留恢复的备份和恢复前的备份，实际为 {}", remaining);
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_backup_prune_keep() -> Result<()> {
+    use claude_code_config_rs::config::paths::BACKUP_DIR;
+
+    let (_temp_dir, settings_path, _ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+    let backup_dir = settings_path.with_file_name(BACKUP_DIR);
+    std::fs::create_dir_all(&backup_dir).context("创建备份目录失败")?;
+    for id in ["20260101_000000", "20260102_000000", "20260103_000000"] {
+        std::fs::write(
+            backup_dir.join(format!("settings.json.backup.{}", id)),
+            "{}",
+        )
+        .context("写入备份失败")?;
+    }
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("backup")
+        .arg("prune")
+        .arg("--keep")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("已清理 2 个备份"));
+
+    if !backup_dir
+        .join("settings.json.backup.20260103_000000")
+        .exists()
+    {
+        anyhow::bail!("最新备份应被保留");
+    }
+    if backup_dir
+        .join("settings.json.backup.20260101_000000")
+        .exists()
+    {
+        anyhow::bail!("旧备份应被删除");
+    }
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("backup").arg("prune").assert().failure();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_use_applies_backup_retention() -> Result<()> {
+    use claude_code_config_rs::config::paths::BACKUP_DIR;
+
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "retained": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.retained.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-retained"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    // 旧版本存放在 settings.json 旁边的备份和备份目录中的备份
+    let backup_dir = settings_path.with_file_name(BACKUP_DIR);
+    std::fs::create_dir_all(&backup_dir).context("创建备份目录失败")?;
+    std::fs::write(
+        settings_path.with_file_name("settings.json.backup.20260101_000000"),
+        "{}",
+    )
+    .context("写入备份失败")?;
+    std::fs::write(
+        backup_dir.join("settings.json.backup.20260102_000000"),
+        "{}",
+    )
+    .context("写入备份失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("backup")
+        .arg("retention")
+        .arg("--keep")
+        .arg("2")
+        .assert()
+        .success();
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("use").arg("retained").assert().success();
+
+    // 旧版本的备份被移动到备份目录后参与清理：新备份加上最近的一个旧备份，最早的旧备份被自动清理
+    if settings_path
+        .with_file_name("settings.json.backup.20260101_000000")
+        .exists()
+    {
+        anyhow::bail!("超出保留数量的备份应被清理");
+    }
+    let remaining = std::fs::read_dir(&backup_dir)
+        .context("读取备份目录失败")?
+        .count();
+    if remaining != 2 {
+        anyhow::bail!("备份目录中应保留 2 个备份，实际为 {}", remaining);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn test_exec_sets_profile_env() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+    let original_settings =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+
+    let config = r#"{
+        "profiles": {
+            "exec": {
+                "api_key_helper": "echo sk-from-helper",
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.exec.com",
+                    "ANTHROPIC_MODEL": "exec-model"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("exec")
+        .arg("exec")
+        .arg("--")
+        .arg("sh")
+        .arg("-c")
+        .arg("echo \"$ANTHROPIC_BASE_URL $ANTHROPIC_MODEL $ANTHROPIC_API_KEY\"")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "https://api.exec.com exec-model sk-from-helper",
+        ));
+
+    // settings.json 不应被修改
+    let settings_content =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+    if settings_content != original_settings {
+        anyhow::bail!("exec 不应修改 settings.json");
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_env_rejects_invalid_key() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let config = r#"{
+        "profiles": {
+            "b": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.b.com",
+                    "X;touch /tmp/pwned;Y": "v"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    // 无效的变量名不应出现在 eval 的 shell 语句中
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.args(["env", "b", "--shell", "bash"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("touch").not())
+        .stderr(predicate::str::contains("无效的环境变量名"));
+
+    // 从 settings.json 导入时同样校验变量名
+    std::fs::write(&ccc_config_path, r#"{"profiles": {}, "current": null}"#)
+        .context("写入配置失败")?;
+    std::fs::write(
+        &settings_path,
+        r#"{"env": {
+            "ANTHROPIC_BASE_URL": "https://api.b.com",
+            "ANTHROPIC_AUTH_TOKEN": "sk-b",
+            "$(touch /tmp/pwned)": "v"
+        }}"#,
+    )
+    .context("写入 settings 失败")?;
+    let (ok, value) = run_json(&["--output", "json", "import", "b"])?;
+    if ok || field(&value, "/error/code") != "invalid_env_key" {
+        anyhow::bail!("导入无效的变量名应失败: {}", value);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn test_exec_removes_inherited_env() -> Result<()> {
+    let (_temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let config = r#"{
+        "profiles": {
+            "a": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.a.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-a",
+                    "ANTHROPIC_MODEL": "a-model"
+                }
+            },
+            "b": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.b.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-b"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    // 父进程中残留的 API Key 和其他配置档案的变量不应传给子进程
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.args(["exec", "b", "--", "sh", "-c"])
+        .arg("echo \"[$ANTHROPIC_API_KEY] [$ANTHROPIC_MODEL] $ANTHROPIC_AUTH_TOKEN [$OTHER_VAR]\"")
+        .env("ANTHROPIC_API_KEY", "sk-stale")
+        .env("ANTHROPIC_MODEL", "a-model")
+        .env("ANTHROPIC_AUTH_TOKEN", "sk-a")
+        .env("OTHER_VAR", "kept")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[] [] sk-b [kept]"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn test_exec_forwards_exit_code() -> Result<()> {
+    let (_temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let config = r#"{
+        "profiles": {
+            "exit": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.exit.com"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("run")
+        .arg("exit")
+        .arg("sh")
+        .arg("-c")
+        .arg("exit 3")
+        .assert()
+        .code(3);
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("exec")
+        .arg("nonexistent")
+        .arg("true")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("不存在"));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_env_outputs_shell_exports() -> Result<()> {
+    let (_temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-it's"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("env")
+        .arg("kimi")
+        .arg("--shell")
+        .arg("bash")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "export ANTHROPIC_BASE_URL='https://api.moonshot.cn/anthropic'",
+        ))
+        .stdout(predicate::str::contains(
+            r"export ANTHROPIC_AUTH_TOKEN='sk-it'\''s'",
+        ));
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("env")
+        .arg("kimi")
+        .arg("--shell")
+        .arg("fish")
+        .arg("--unset")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("set -e ANTHROPIC_AUTH_TOKEN"))
+        .stdout(predicate::str::contains("set -e ANTHROPIC_BASE_URL"));
+
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("env").assert().failure();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn test_env_output_can_be_evaluated() -> Result<()> {
+    let (_temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let config = r#"{
+        "profiles": {
+            "quoted": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.quoted.com",
+                    "ANTHROPIC_CUSTOM_HEADERS": "X-Test: a 'b' $c"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let output = Command::new(cargo_bin!("cccrs"))
+        .arg("env")
+        .arg("quoted")
+        .arg("--shell")
+        .arg("bash")
+        .output()
+        .context("运行 cccrs env 失败")?;
+    let script = String::from_utf8(output.stdout).context("输出不是 UTF-8")?;
+
+    let mut sh = Command::new("sh");
+    sh.arg("-c")
+        .arg(format!(
+            "{}\nprintf '%s' \"$ANTHROPIC_CUSTOM_HEADERS\"",
+            script
+        ))
+        .assert()
+        .success()
+        .stdout("X-Test: a 'b' $c");
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_use_project_and_local_scope() -> Result<()> {
+    let (temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+    let original_settings =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+
+    let config = r#"{
+        "profiles": {
+            "client": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.client.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-client"
+                }
+            },
+            "headers": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.client.com",
+                    "ANTHROPIC_CUSTOM_HEADERS": "x-api-key: sk-client"
+                }
+            },
+            "public": {
+                "env": { "ANTHROPIC_BASE_URL": "https://api.client.com" }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    // 在项目子目录中运行，应从当前目录向上找到项目根目录
+    let project = temp_dir.path().join("project");
+    let nested = project.join("src");
+    std::fs::create_dir_all(&nested).context("创建项目目录失败")?;
+    std::fs::create_dir_all(project.join(".git")).context("创建 .git 目录失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.current_dir(&nested)
+        .arg("use")
+        .arg("client")
+        .arg("--scope")
+        .arg("local")
+        .assert()
+        .success();
+
+    let local_path = project.join(".claude").join("settings.local.json");
+    let local_content =
+        std::fs::read_to_string(&local_path).context("读取 settings.local.json 失败")?;
+    if !local_content.contains("https://api.client.com") {
+        anyhow::bail!("settings.local.json 未写入配置");
+    }
+
+    // 项目级 settings.json 通常会提交到版本库，写入时保留原有权限
+    let project_path = project.join(".claude").join("settings.json");
+    std::fs::write(&project_path, "{}").context("写入 settings.json 失败")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&project_path, std::fs::Permissions::from_mode(0o644))
+            .context("设置权限失败")?;
+    }
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.current_dir(&nested)
+        .arg("use")
+        .arg("client")
+        .arg("--scope")
+        .arg("project")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--scope local"));
+    if !std::fs::read_to_string(&project_path)
+        .context("读取 settings.json 失败")?
+        .contains("https://api.client.com")
+    {
+        anyhow::bail!("项目级 settings.json 未写入配置");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&project_path)
+            .context("获取文件元数据失败")?
+            .permissions()
+            .mode();
+        if mode & 0o777 != 0o644 {
+            anyhow::bail!("项目级 settings.json 的权限应保持不变: {:o}", mode);
+        }
+    }
+
+    // 所有敏感的环境变量都会触发提示，不包含敏感信息时不提示
+    let mut headers = Command::new(cargo_bin!("cccrs"));
+    headers
+        .current_dir(&nested)
+        .args(["use", "headers", "--scope", "project"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--scope local"));
+    let mut public = Command::new(cargo_bin!("cccrs"));
+    public
+        .current_dir(&nested)
+        .args(["use", "public", "--scope", "project"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--scope local").not());
+
+    // 用户级 settings.json 不应被修改
+    let settings_content =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+    if settings_content != original_settings {
+        anyhow::bail!("用户级 settings.json 不应被修改");
+    }
+
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.current_dir(&nested)
+        .arg("list")
+        .arg("--scope")
+        .arg("local")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("当前活跃配置: client"));
+
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.current_dir(&nested)
+        .arg("import")
+        .arg("from-local")
+        .arg("--scope")
+        .arg("local")
+        .assert()
+        .success();
+    let config_content = std::fs::read_to_string(&ccc_config_path).context("读取配置文件失败")?;
+    if !config_content.contains("from-local") {
+        anyhow::bail!("未从 settings.local.json 导入配置");
+    }
+
+    // 不在项目中（包括用户主目录）时不应回退到当前目录
+    let home = settings_path
+        .parent()
+        .and_then(std::path::Path::parent)
+        .context("获取 HOME 目录失败")?;
+    let plain = temp_dir.path().join("plain");
+    std::fs::create_dir_all(&plain).context("创建目录失败")?;
+    for dir in [home, plain.as_path()] {
+        let mut cmd5 = Command::new(cargo_bin!("cccrs"));
+        cmd5.current_dir(dir)
+            .args(["use", "client", "--scope", "project"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("未找到项目根目录"));
+    }
+    if plain.join(".claude").exists() {
+        anyhow::bail!("不应在非项目目录中创建 .claude");
+    }
+    let user_settings =
+        std::fs::read_to_string(&settings_path).context("读取 settings 文件失败")?;
+    if user_settings != original_settings {
+        anyhow::bail!("用户级 settings.json 不应被修改");
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_claude_config_dir_env_and_flag() -> Result<()> {
+    let (temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+
+    // 通过 CLAUDE_CONFIG_DIR 使用另一个 Claude 配置目录
+    let alt_dir = temp_dir.path().join("alt-claude");
+    std::fs::create_dir_all(&alt_dir).context("创建目录失败")?;
+    create_initial_settings(&alt_dir.join("settings.json"))?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.env("CLAUDE_CONFIG_DIR", &alt_dir)
+        .arg("import")
+        .arg("alt")
+        .assert()
+        .success();
+    if !alt_dir.join("cccrs-config.json").exists() {
+        anyhow::bail!("配置文件应创建在 CLAUDE_CONFIG_DIR 中");
+    }
+    if ccc_config_path.exists() {
+        anyhow::bail!("不应创建默认位置的配置文件");
+    }
+
+    // --config-dir 优先于 CLAUDE_CONFIG_DIR
+    let flag_dir = temp_dir.path().join("flag-claude");
+    std::fs::create_dir_all(&flag_dir).context("创建目录失败")?;
+    create_initial_settings(&flag_dir.join("settings.json"))?;
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.env("CLAUDE_CONFIG_DIR", &alt_dir)
+        .arg("--config-dir")
+        .arg(&flag_dir)
+        .arg("import")
+        .arg("flag")
+        .assert()
+        .success();
+    let flag_config =
+        std::fs::read_to_string(flag_dir.join("cccrs-config.json")).context("读取配置失败")?;
+    if !flag_config.contains("flag") || flag_config.contains("\"alt\"") {
+        anyhow::bail!("--config-dir 未生效");
+    }
+
+    // 全局参数也可以放在子命令之后
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("list")
+        .arg("--config-dir")
+        .arg(&flag_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("flag"));
+
+    // exec 启动的命令使用同一个 Claude 配置目录
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.env("CLAUDE_CONFIG_DIR", &alt_dir)
+        .arg("--config-dir")
+        .arg(&flag_dir)
+        .args([
+            "exec",
+            "flag",
+            "--",
+            "sh",
+            "-c",
+            "echo \"dir=$CLAUDE_CONFIG_DIR\"",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "dir={}",
+            flag_dir.display()
+        )));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_cccrs_config_env_override() -> Result<()> {
+    let (temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+
+    create_initial_settings(&settings_path)?;
+
+    let custom_config = temp_dir.path().join("dotfiles").join("cccrs.json");
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.env("CCCRS_CONFIG", &custom_config)
+        .arg("import")
+        .arg("custom")
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(&custom_config).context("读取自定义配置失败")?;
+    if !content.contains("custom") {
+        anyhow::bail!("配置未写入 CCCRS_CONFIG 指定的文件");
+    }
+    if ccc_config_path.exists() {
+        anyhow::bail!("不应创建默认位置的配置文件");
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_edit_set_unset_reapplies_active_profile() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi",
+                    "ANTHROPIC_MODEL": "kimi-k1"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("use").arg("kimi").assert().success();
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("edit")
+        .arg("kimi")
+        .arg("--set")
+        .arg("API_TIMEOUT_MS=600000")
+        .arg("--unset")
+        .arg("ANTHROPIC_MODEL")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("已将修改应用到 settings.json"));
+
+    let settings = std::fs::read_to_string(&settings_path).context("读取 settings 失败")?;
+    if !settings.contains("API_TIMEOUT_MS") {
+        anyhow::bail!("新增的环境变量未写入 settings.json");
+    }
+    if settings.contains("ANTHROPIC_MODEL") {
+        anyhow::bail!("删除的环境变量应从 settings.json 移除");
+    }
+    if !settings.contains("otherField") {
+        anyhow::bail!("用户字段不应被移除");
+    }
+
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("edit")
+        .arg("kimi")
+        .arg("--unset")
+        .arg("NOT_PRESENT")
+        .assert()
+        .failure();
+
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.arg("edit")
+        .arg("missing")
+        .arg("--set")
+        .arg("A=b")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("不存在"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn test_edit_in_editor() -> Result<()> {
+    let (temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("edit")
+        .arg("kimi")
+        .env_remove("VISUAL")
+        .env("EDITOR", "sed -i s/sk-kimi/sk-edited/")
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?;
+    if !content.contains("sk-edited") {
+        anyhow::bail!("编辑结果未保存");
+    }
+
+    // 校验失败时不修改配置
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("edit")
+        .arg("kimi")
+        .env_remove("VISUAL")
+        .env("EDITOR", "sed -i s/env/broken/")
+        .assert()
+        .failure();
+
+    let unchanged = std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?;
+    if unchanged != content {
+        anyhow::bail!("校验失败时配置不应被修改");
+    }
+
+    // 配置目录中包含引号、`$` 和反引号时不应被 shell 解析
+    let weird_dir = temp_dir
+        .path()
+        .join("we\"ird $(touch pwned) `touch pwned2`");
+    std::fs::create_dir_all(&weird_dir).context("创建目录失败")?;
+    std::fs::write(weird_dir.join("cccrs-config.json"), config).context("写入配置失败")?;
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.current_dir(temp_dir.path())
+        .arg("--config-dir")
+        .arg(&weird_dir)
+        .args(["edit", "kimi"])
+        .env_remove("VISUAL")
+        .env("EDITOR", "sed -i s/sk-kimi/sk-weird/")
+        .assert()
+        .success();
+    let weird =
+        std::fs::read_to_string(weird_dir.join("cccrs-config.json")).context("读取配置失败")?;
+    if !weird.contains("sk-weird") {
+        anyhow::bail!("编辑结果未保存到包含特殊字符的配置目录");
+    }
+    if temp_dir.path().join("pwned").exists() || temp_dir.path().join("pwned2").exists() {
+        anyhow::bail!("路径中的命令不应被执行");
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_rename_and_copy_commands() -> Result<()> {
+    let (_temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi"
+                }
+            },
+            "work": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.anthropic.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-work"
+                }
+            }
+        },
+        "current": "kimi"
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("rename")
+        .arg("kimi")
+        .arg("moonshot")
+        .assert()
+        .success();
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("copy")
+        .arg("moonshot")
+        .arg("moonshot-2")
+        .assert()
+        .success();
+
+    // 目标已存在时需要 --force
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("copy")
+        .arg("work")
+        .arg("moonshot-2")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("已存在"));
+
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.arg("rename")
+        .arg("work")
+        .arg("moonshot-2")
+        .arg("--force")
+        .assert()
+        .success();
+
+    let mut cmd5 = Command::new(cargo_bin!("cccrs"));
+    cmd5.arg("rename")
+        .arg("moonshot")
+        .arg("bad name")
+        .assert()
+        .failure();
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?)
+            .context("解析配置失败")?;
+    if saved.get("current") != Some(&serde_json::json!("moonshot")) {
+        anyhow::bail!("重命名后当前配置记录未更新: {}", saved);
+    }
+    let mut names: Vec<_> = saved
+        .get("profiles")
+        .and_then(serde_json::Value::as_object)
+        .context("获取 profiles 失败")?
+        .keys()
+        .cloned()
+        .collect();
+    names.sort();
+    if names != ["moonshot", "moonshot-2"] {
+        anyhow::bail!("配置档案列表不匹配: {:?}", names);
+    }
+    let token = saved.pointer("/profiles/moonshot-2/env/ANTHROPIC_AUTH_TOKEN");
+    if token != Some(&serde_json::json!("sk-work")) {
+        anyhow::bail!("--force 覆盖结果不匹配: {:?}", token);
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_show_masks_and_reveals_secrets() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi-0123456789",
+                    "ANTHROPIC_MODEL": "kimi-k2"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("show")
+        .arg("kimi")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("sk-k****"))
+        .stdout(predicate::str::contains("6789").not())
+        .stdout(predicate::str::contains("ANTHROPIC_MODEL = kimi-k2"))
+        .stdout(predicate::str::contains("不一致"));
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("use").arg("kimi").assert().success();
+
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("show")
+        .arg("kimi")
+        .arg("--reveal")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("sk-kimi-0123456789"))
+        .stdout(predicate::str::contains("一致"))
+        .stdout(predicate::str::contains("不一致").not());
+
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.arg("show")
+        .arg("missing")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("不存在"));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_profile_extends() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "base": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.shared.com",
+                    "ANTHROPIC_MODEL": "shared-model"
+                }
+            },
+            "alice": {
+                "extends": "base",
+                "env": {
+                    "ANTHROPIC_AUTH_TOKEN": "sk-alice-0123456789"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("use").arg("alice").assert().success();
+
+    let settings: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&settings_path).context("读取 settings 失败")?,
+    )
+    .context("解析 settings 失败")?;
+    let env = settings.get("env").context("缺少 env")?;
+    if env.get("ANTHROPIC_BASE_URL") != Some(&serde_json::json!("https://api.shared.com"))
+        || env.get("ANTHROPIC_MODEL") != Some(&serde_json::json!("shared-model"))
+        || env.get("ANTHROPIC_AUTH_TOKEN") != Some(&serde_json::json!("sk-alice-0123456789"))
+    {
+        anyhow::bail!("继承的字段未写入 settings.json: {}", env);
+    }
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("show")
+        .arg("alice")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("alice -> base"))
+        .stdout(predicate::str::contains("原始字段"))
+        .stdout(predicate::str::contains("解析结果"));
+
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("* alice (当前)"));
+
+    // 被继承的配置不能删除
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.arg("remove")
+        .arg("base")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("alice"));
+
+    // 循环继承会被拒绝
+    let mut cmd5 = Command::new(cargo_bin!("cccrs"));
+    cmd5.arg("edit")
+        .arg("base")
+        .arg("--set")
+        .arg("extends=alice")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("循环"));
+
+    // 修改父配置时重新写入当前活跃的子配置
+    let mut cmd6 = Command::new(cargo_bin!("cccrs"));
+    cmd6.arg("edit")
+        .arg("base")
+        .arg("--set")
+        .arg("ANTHROPIC_MODEL=new-model")
+        .assert()
+        .success();
+    let updated = std::fs::read_to_string(&settings_path).context("读取 settings 失败")?;
+    if !updated.contains("new-model") {
+        anyhow::bail!("修改父配置后未重新写入 settings.json");
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_placeholders_resolved_on_use() -> Result<()> {
+    let (temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+
+    let secret_path = temp_dir.path().join("model");
+    std::fs::write(&secret_path, "model-from-file\n").context("写入文件失败")?;
+
+    let config = format!(
+        r#"{{
+        "profiles": {{
+            "kimi": {{
+                "env": {{
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "${{env:CCCRS_TEST_KIMI_KEY}}",
+                    "ANTHROPIC_MODEL": "${{file:{}}}"
+                }}
+            }}
+        }},
+        "current": null
+    }}"#,
+        secret_path.display()
+    );
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    // 环境变量未设置时报错，并指明配置档案和字段
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("use")
+        .arg("kimi")
+        .env_remove("CCCRS_TEST_KIMI_KEY")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("kimi"))
+        .stderr(predicate::str::contains("ANTHROPIC_AUTH_TOKEN"))
+        .stderr(predicate::str::contains("CCCRS_TEST_KIMI_KEY"));
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.arg("use")
+        .arg("kimi")
+        .env("CCCRS_TEST_KIMI_KEY", "sk-from-env")
+        .assert()
+        .success();
+
+    let settings = std::fs::read_to_string(&settings_path).context("读取 settings 失败")?;
+    if !settings.contains("sk-from-env") || !settings.contains("model-from-file") {
+        anyhow::bail!("占位符未展开: {}", settings);
+    }
+    let saved = std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?;
+    if saved.contains("sk-from-env") {
+        anyhow::bail!("配置文件中应保留占位符");
+    }
+
+    // 包含占位符的配置仍能被检测为当前配置
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("* kimi (当前)"));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_vault_encrypt_and_decrypt() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi-vault-key"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    // 没有口令且 stdin 不是终端时报错
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.args(["vault", "encrypt"])
+        .env_remove("CCCRS_PASSPHRASE")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("CCCRS_PASSPHRASE"));
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.args(["vault", "encrypt"])
+        .env("CCCRS_PASSPHRASE", "correct horse")
+        .assert()
+        .success();
+
+    let saved = std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?;
+    if saved.contains("sk-kimi-vault-key") || !saved.contains("${vault:kimi.ANTHROPIC_AUTH_TOKEN}")
+    {
+        anyhow::bail!("敏感字段应被替换为加密存储占位符: {}", saved);
+    }
+    let vault_path = ccc_config_path.with_file_name("cccrs-vault.json");
+    let vault = std::fs::read_to_string(&vault_path).context("读取加密存储失败")?;
+    if vault.contains("sk-kimi-vault-key") {
+        anyhow::bail!("加密存储中不应包含明文");
+    }
+
+    // 口令错误时无法使用配置
+    let output = Command::new(cargo_bin!("cccrs"))
+        .args(["--output", "json", "use", "kimi"])
+        .env("CCCRS_PASSPHRASE", "wrong")
+        .output()
+        .context("运行 cccrs 失败")?;
+    let failed: serde_json::Value =
+        serde_json::from_slice(&output.stdout).context("输出不是有效的 JSON")?;
+    if output.status.success() || field(&failed, "/error/code") != "wrong_passphrase" {
+        anyhow::bail!("错误口令的输出不匹配: {}", failed);
+    }
+
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.args(["use", "kimi"])
+        .env("CCCRS_PASSPHRASE", "correct horse")
+        .assert()
+        .success();
+    let settings = std::fs::read_to_string(&settings_path).context("读取 settings 失败")?;
+    if !settings.contains("sk-kimi-vault-key") {
+        anyhow::bail!("使用配置时应解密敏感字段: {}", settings);
+    }
+
+    let (status_ok, status) = run_json(&["--output", "json", "vault", "status"])?;
+    if !status_ok
+        || field(&status, "/result/enabled") != true
+        || field(&status, "/result/encrypted/0/key") != "ANTHROPIC_AUTH_TOKEN"
+    {
+        anyhow::bail!("vault status 输出不匹配: {}", status);
+    }
+
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.args(["vault", "decrypt"])
+        .env("CCCRS_PASSPHRASE", "correct horse")
+        .assert()
+        .success();
+    let restored = std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?;
+    if !restored.contains("sk-kimi-vault-key") || vault_path.exists() {
+        anyhow::bail!("decrypt 应还原明文并删除加密存储: {}", restored);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn test_secret_files_are_private() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi-key"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+    for path in [&settings_path, &ccc_config_path] {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o644))
+            .context("设置权限失败")?;
+    }
+    let mode = |path: &std::path::Path| -> Result<u32> {
+        Ok(std::fs::metadata(path)
+            .context("获取文件元数据失败")?
+            .permissions()
+            .mode()
+            & 0o777)
+    };
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("cccrs permissions --fix"));
+
+    let (check_ok, checked) = run_json(&["--output", "json", "permissions"])?;
+    if !check_ok
+        || *field(&checked, "/result/insecure/0/path") != *ccc_config_path.to_string_lossy()
+        || field(&checked, "/result/insecure/0/mode") != "644"
+        || *field(&checked, "/result/insecure/1/path") != *settings_path.to_string_lossy()
+    {
+        anyhow::bail!("permissions 输出不匹配: {}", checked);
+    }
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.args(["permissions", "--fix"]).assert().success();
+    if mode(&ccc_config_path)? != 0o600 || mode(&settings_path)? != 0o600 {
+        anyhow::bail!("--fix 后文件权限应为 600");
+    }
+
+    // 重新写入时同样收紧权限，备份也为 600
+    std::fs::set_permissions(&ccc_config_path, std::fs::Permissions::from_mode(0o644))
+        .context("设置权限失败")?;
+    let (use_ok, used) = run_json(&["--output", "json", "use", "kimi"])?;
+    let backup_path = field(&used, "/result/backup_path")
+        .as_str()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("use 输出缺少备份路径: {}", used))?;
+    if !use_ok
+        || mode(&ccc_config_path)? != 0o600
+        || mode(&settings_path)? != 0o600
+        || mode(&backup_path)? != 0o600
+    {
+        anyhow::bail!("配置文件、settings 和备份的权限应为 600");
+    }
+
+    let mut cmd3 = Command::new(cargo_bin!("cccrs"));
+    cmd3.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("permissions --fix").not());
+
+    // 提交到仓库的项目 settings.json 不包含敏感信息时不检查
+    let project = temp_dir.path().join("project");
+    std::fs::create_dir_all(project.join(".git")).context("创建目录失败")?;
+    std::fs::create_dir_all(project.join(".claude")).context("创建目录失败")?;
+    let project_path = project.join(".claude").join("settings.json");
+    std::fs::write(
+        &project_path,
+        r#"{"env": {"ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic"}}"#,
+    )
+    .context("写入 settings.json 失败")?;
+    std::fs::set_permissions(&project_path, std::fs::Permissions::from_mode(0o644))
+        .context("设置权限失败")?;
+    let mut project_list = Command::new(cargo_bin!("cccrs"));
+    project_list
+        .current_dir(&project)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("permissions --fix").not());
+
+    // 检查当前项目中写入了 API Key 的项目级 settings
+    let local_path = project.join(".claude").join("settings.local.json");
+    std::fs::write(
+        &local_path,
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "sk-local"}}"#,
+    )
+    .context("写入 settings.local.json 失败")?;
+    std::fs::set_permissions(&local_path, std::fs::Permissions::from_mode(0o644))
+        .context("设置权限失败")?;
+    let mut cmd4 = Command::new(cargo_bin!("cccrs"));
+    cmd4.current_dir(&project)
+        .args(["permissions", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("settings.local.json"));
+    if mode(&local_path)? != 0o600 || mode(&project_path)? != 0o644 {
+        anyhow::bail!("项目本地 settings 的权限应为 600，项目 settings 的权限应保持不变");
+    }
+
+    // 项目 settings.json 中写入了敏感的环境变量时同样检查
+    std::fs::write(
+        &project_path,
+        r#"{"env": {"ANTHROPIC_CUSTOM_HEADERS": "x-api-key: sk-project"}}"#,
+    )
+    .context("写入 settings.json 失败")?;
+    let mut cmd5 = Command::new(cargo_bin!("cccrs"));
+    cmd5.current_dir(&project)
+        .arg("permissions")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            project_path.to_string_lossy().into_owned(),
+        ));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_doctor_reports_problems() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+    let config = r#"{
+        "profiles": {
+            "helper": {
+                "api_key_helper": "echo helper-key",
+                "env": { "ANTHROPIC_BASE_URL": "https://api.example.com" }
+            },
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi-key"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let doctor = |shell_key: Option<&str>| -> Result<(bool, serde_json::Value)> {
+        let mut cmd = Command::new(cargo_bin!("cccrs"));
+        cmd.args(["--output", "json", "doctor"])
+            .env_remove("ANTHROPIC_API_KEY")
+            .env_remove("ANTHROPIC_AUTH_TOKEN")
+            .env_remove("ANTHROPIC_BASE_URL");
+        if let Some(key) = shell_key {
+            cmd.env("ANTHROPIC_API_KEY", key);
+        }
+        let output = cmd.output().context("运行 cccrs 失败")?;
+        let report = serde_json::from_slice(&output.stdout).context("输出不是有效的 JSON")?;
+        Ok((output.status.success(), report))
+    };
+    let statuses = |report: &serde_json::Value| -> Vec<(String, String)> {
+        field(report, "/result/checks")
+            .as_array()
+            .map(|checks| {
+                checks
+                    .iter()
+                    .filter(|check| field(check, "/status") != "pass")
+                    .map(|check| {
+                        (
+                            field(check, "/id").as_str().unwrap_or_default().to_string(),
+                            field(check, "/status")
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let expect = |report: &serde_json::Value, expected: &[(&str, &str)]| -> Result<()> {
+        let actual = statuses(report);
+        if actual
+            .iter()
+            .map(|(id, status)| (id.as_str(), status.as_str()))
+            .collect::<Vec<_>>()
+            != expected
+        {
+            anyhow::bail!("诊断结果不匹配: {}", report);
+        }
+        Ok(())
+    };
+
+    // 切换配置后残留上一个配置的 apiKeyHelper，shell 中的 API Key 会覆盖 settings
+    for name in ["helper", "kimi"] {
+        let mut cmd = Command::new(cargo_bin!("cccrs"));
+        cmd.args(["use", name]).assert().success();
+    }
+    let (warn_ok, warned) = doctor(Some("sk-shell"))?;
+    if !warn_ok {
+        anyhow::bail!("只有警告时应以成功状态码退出: {}", warned);
+    }
+    expect(
+        &warned,
+        &[("shell_env", "warn"), ("api_key_helper", "warn")],
+    )?;
+    if !warned.to_string().contains("cccrs use kimi --clean") {
+        anyhow::bail!("应建议使用 --clean 移除残留字段: {}", warned);
+    }
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.args(["use", "kimi", "--clean"]).assert().success();
+    let (clean_ok, cleaned) = doctor(None)?;
+    if !clean_ok {
+        anyhow::bail!("修复后应以成功状态码退出: {}", cleaned);
+    }
+    expect(&cleaned, &[])?;
+
+    // 当前配置指向已删除的配置档案、继承的父配置不存在时检查失败
+    let broken = r#"{
+        "profiles": {
+            "kimi": { "extends": "missing" }
+        },
+        "current": "deleted"
+    }"#;
+    std::fs::write(&ccc_config_path, broken).context("写入配置失败")?;
+    let (broken_ok, report) = doctor(None)?;
+    if broken_ok {
+        anyhow::bail!("存在失败项时应以失败状态码退出: {}", report);
+    }
+    expect(&report, &[("config_schema", "fail"), ("current", "fail")])?;
+    Ok(())
+}
+
+/// 模拟 API 对 `POST /v1/messages` 返回的 SSE 响应
+const MOCK_SSE: &str = "event: message_start\ndata: {\"type\":\"message_start\"}\n\nevent: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+
+/// 启动模拟 Anthropic API 的本地服务器，返回其地址
+///
+/// 使用 `valid_key`（`Authorization: Bearer` 或 `x-api-key`）的请求返回 200，否则返回 401；
+/// `/legacy` 前缀下不支持 `/v1/models`，只支持 `POST /v1/messages`；`/overloaded` 前缀下总是返回 529。
+/// `POST /v1/messages` 返回 [`MOCK_SSE`]
+fn start_mock_api(valid_key: &'static str) -> Result<String> {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").context("启动模拟服务器失败")?;
+    let addr = listener.local_addr().context("获取模拟服务器地址失败")?;
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().filter_map(std::result::Result::ok) {
+            let mut reader = BufReader::new(&mut stream);
+            let mut request_line = String::new();
+            let _ = reader.read_line(&mut request_line);
+            let mut authorized = false;
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+                    break;
+                }
+                let lower = line.trim().to_ascii_lowercase();
+                if lower == format!("authorization: bearer {}", valid_key)
+                    || lower == format!("x-api-key: {}", valid_key)
+                {
+                    authorized = true;
+                }
+                if let Some(length) = lower.strip_prefix("content-length: ") {
+                    content_length = length.parse().unwrap_or(0);
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            let _ = reader.read_exact(&mut request_body);
+
+            let (status, body) = if request_line.starts_with("GET /legacy/v1/models") {
+                ("404 Not Found", r#"{"error":{"message":"not found"}}"#)
+            } else if request_line.contains(" /overloaded/") {
+                ("529 Overloaded", r#"{"error":{"message":"overloaded"}}"#)
+            } else if !authorized {
+                (
+                    "401 Unauthorized",
+                    r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#,
+                )
+            } else if request_line.starts_with("POST /v1/messages") {
+                ("200 OK", MOCK_SSE)
+            } else {
+                ("200 OK", r#"{"data":[]}"#)
+            };
+            let content_type = if body == MOCK_SSE {
+                "text/event-stream"
+            } else {
+                "application/json"
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                content_type,
+                body.len(),
+                body
+            );
+        }
+    });
+    Ok(format!("http://{}", addr))
+}
+
+#[test]
+#[serial]
+fn test_test_command_checks_endpoints() -> Result<()> {
+    let (_temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+    let base_url = start_mock_api("sk-valid")?;
+    // 获取一个没有服务监听的端口
+    let closed = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .context("获取空闲端口失败")?;
+
+    let config = serde_json::json!({
+        "profiles": {
+            "good": { "env": {
+                "ANTHROPIC_BASE_URL": base_url,
+                "ANTHROPIC_AUTH_TOKEN": "sk-valid"
+            } },
+            "expired": { "env": {
+                "ANTHROPIC_BASE_URL": base_url,
+                "ANTHROPIC_AUTH_TOKEN": "sk-expired"
+            } },
+            "helper": {
+                "api_key_helper": "echo sk-valid",
+                "env": { "ANTHROPIC_BASE_URL": format!("{}/legacy", base_url) }
+            },
+            "down": { "env": {
+                "ANTHROPIC_BASE_URL": format!("http://{}", closed),
+                "ANTHROPIC_AUTH_TOKEN": "sk-valid"
+            } }
+        },
+        "current": null
+    });
+    std::fs::write(&ccc_config_path, config.to_string()).context("写入配置失败")?;
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.args(["test", "good"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("good  200"));
+
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.args(["test", "expired"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("认证失败: invalid x-api-key"));
+
+    let (all_ok, report) = run_json(&["--output", "json", "test", "--all", "--timeout", "5"])?;
+    if all_ok {
+        anyhow::bail!("存在失败的配置时应以失败状态码退出: {}", report);
+    }
+    let expected = [
+        ("down", "unreachable", serde_json::Value::Null),
+        ("expired", "auth_error", serde_json::json!(401)),
+        ("good", "ok", serde_json::json!(200)),
+        ("helper", "ok", serde_json::json!(200)),
+    ];
+    for (index, (name, outcome, status)) in expected.iter().enumerate() {
+        let result = field(&report, &format!("/result/results/{}", index));
+        if field(result, "/profile") != name
+            || field(result, "/outcome") != outcome
+            || field(result, "/status") != status
+        {
+            anyhow::bail!("配置 {} 的检查结果不匹配: {}", name, report);
+        }
+    }
+    // 不支持 /v1/models 的端点改用 /v1/messages 检查
+    if !field(&report, "/result/results/3/url")
+        .as_str()
+        .is_some_and(|url| url.ends_with("/legacy/v1/messages"))
+    {
+        anyhow::bail!("应改用 /v1/messages 检查: {}", report);
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_bench_ranks_profiles() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+    let base_url = start_mock_api("sk-valid")?;
+    let closed = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .context("获取空闲端口失败")?;
+
+    let config = serde_json::json!({
+        "profiles": {
+            "good": { "env": {
+                "ANTHROPIC_BASE_URL": base_url,
+                "ANTHROPIC_AUTH_TOKEN": "sk-valid"
+            } },
+            "expired": { "env": {
+                "ANTHROPIC_BASE_URL": base_url,
+                "ANTHROPIC_AUTH_TOKEN": "sk-expired"
+            } },
+            "down": { "env": {
+                "ANTHROPIC_BASE_URL": format!("http://{}", closed),
+                "ANTHROPIC_AUTH_TOKEN": "sk-valid"
+            } }
+        },
+        "current": null
+    });
+    std::fs::write(&ccc_config_path, config.to_string()).context("写入配置失败")?;
+
+    let (ok, report) = run_json(&["--output", "json", "bench", "-n", "3", "--timeout", "5"])?;
+    if !ok || field(&report, "/result/best") != "good" || !field(&report, "/result/used").is_null()
+    {
+        anyhow::bail!("最快的配置应为 good 且不自动切换: {}", report);
+    }
+    let first = field(&report, "/result/results/0");
+    if field(first, "/profile") != "good"
+        || field(first, "/count") != 3
+        || field(first, "/errors") != 0
+        || field(first, "/error_rate") != 0
+        || !field(first, "/p50_ms").is_u64()
+        || !field(first, "/p95_ms").is_u64()
+    {
+        anyhow::bail!("good 的测量结果不正确: {}", report);
+    }
+    for index in 1..=2 {
+        let result = field(&report, &format!("/result/results/{}", index));
+        if field(result, "/error_rate") != 100 || !field(result, "/p50_ms").is_null() {
+            anyhow::bail!("失败的配置错误率应为 100%: {}", report);
+        }
+    }
+
+    // 只测量失败的配置时以失败状态码退出
+    let (failed_ok, failed) = run_json(&["--output", "json", "bench", "expired", "down"])?;
+    if failed_ok || !field(&failed, "/result/best").is_null() {
+        anyhow::bail!("所有配置均不可用时应以失败状态码退出: {}", failed);
+    }
+
+    let (used_ok, used) = run_json(&["--output", "json", "bench", "good", "expired", "--use"])?;
+    if !used_ok || field(&used, "/result/used") != "good" {
+        anyhow::bail!("--use 应切换到最快的配置: {}", used);
+    }
+    let settings: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&settings_path).context("读取 settings.json 失败")?,
+    )
+    .context("解析 settings.json 失败")?;
+    if field(&settings, "/env/ANTHROPIC_BASE_URL") != base_url.as_str() {
+        anyhow::bail!("--use 应写入 settings.json: {}", settings);
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_use_auto_fails_over() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+    let base_url = start_mock_api("sk-valid")?;
+    let closed = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .context("获取空闲端口失败")?;
+
+    let config = serde_json::json!({
+        "profiles": {
+            "primary": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": format!("http://{}", closed),
+                    "ANTHROPIC_AUTH_TOKEN": "sk-valid"
+                },
+                "fallback": ["expired", "good"]
+            },
+            "expired": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": base_url,
+                    "ANTHROPIC_AUTH_TOKEN": "sk-expired"
+                },
+                "fallback": ["primary"]
+            },
+            "good": { "env": {
+                "ANTHROPIC_BASE_URL": base_url,
+                "ANTHROPIC_AUTH_TOKEN": "sk-valid"
+            } }
+        },
+        "current": null
+    });
+    std::fs::write(&ccc_config_path, config.to_string()).context("写入配置失败")?;
+
+    let (ok, report) = run_json(&[
+        "--output",
+        "json",
+        "use",
+        "--auto",
+        "primary",
+        "--timeout",
+        "5",
+    ])?;
+    if !ok || field(&report, "/result/profile") != "good" {
+        anyhow::bail!("应切换到第一个可用的备用配置: {}", report);
+    }
+    let settings: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&settings_path).context("读取 settings.json 失败")?,
+    )
+    .context("解析 settings.json 失败")?;
+    if field(&settings, "/env/ANTHROPIC_AUTH_TOKEN") != "sk-valid" {
+        anyhow::bail!("settings.json 未更新: {}", settings);
+    }
+
+    // 选择结果和跳过原因记录在配置文件中
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?)
+            .context("解析配置失败")?;
+    let record = field(&saved, "/failover");
+    if field(record, "/group") != "primary"
+        || field(record, "/profile") != "good"
+        || field(record, "/skipped/0/profile") != "primary"
+        || !field(record, "/skipped/0/reason")
+            .as_str()
+            .is_some_and(|reason| reason.starts_with("无法连接"))
+        || field(record, "/skipped/1/profile") != "expired"
+        || !field(record, "/skipped/1/reason")
+            .as_str()
+            .is_some_and(|reason| reason.contains("HTTP 401"))
+    {
+        anyhow::bail!("故障切换记录不匹配: {}", saved);
+    }
+    let (_, list) = run_json(&["--output", "json", "list"])?;
+    if field(&list, "/result/failover/profile") != "good" {
+        anyhow::bail!("list 应包含故障切换记录: {}", list);
+    }
+
+    // 所有候选均不可用时报错且不修改 settings.json
+    let (failed_ok, failed) = run_json(&["--output", "json", "use", "--auto", "expired"])?;
+    if failed_ok || field(&failed, "/error/code") != "no_healthy_profile" {
+        anyhow::bail!("所有候选均不可用时应报错: {}", failed);
+    }
+
+    // 被列为备用配置的配置档案无法删除
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.args(["remove", "good"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("primary 的备用配置"));
+
+    // 手动切换后清除记录
+    let mut cmd2 = Command::new(cargo_bin!("cccrs"));
+    cmd2.args(["use", "good"]).assert().success();
+    let cleared: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?)
+            .context("解析配置失败")?;
+    if !field(&cleared, "/failover").is_null() {
+        anyhow::bail!("手动切换后应清除故障切换记录: {}", cleared);
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_proxy_forwards_to_target() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+
+    let (temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+    let base_url = start_mock_api("sk-valid")?;
+    let token_path = temp_dir.path().join("token");
+    std::fs::write(&token_path, "sk-old").context("写入 Token 失败")?;
+    let config = serde_json::json!({
+        "profiles": {
+            "rotating": {
+                "env": { "ANTHROPIC_BASE_URL": base_url },
+                "api_key_helper": format!("cat '{}'", token_path.display())
+            },
+            "good": { "env": {
+                "ANTHROPIC_BASE_URL": base_url,
+                "ANTHROPIC_AUTH_TOKEN": "sk-valid"
+            } },
+            "expired": { "env": {
+                "ANTHROPIC_BASE_URL": base_url,
+                "ANTHROPIC_AUTH_TOKEN": "sk-expired"
+            } },
+            "overloaded": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": format!("{}/overloaded", base_url),
+                    "ANTHROPIC_AUTH_TOKEN": "sk-valid"
+                },
+                "fallback": ["good"]
+            }
+        },
+        "current": "expired"
+    });
+    std::fs::write(&ccc_config_path, config.to_string()).context("写入配置失败")?;
+
+    let mut child = std::process::Command::new(cargo_bin!("cccrs"))
+        .args([
+            "--output",
+            "json",
+            "proxy",
+            "start",
+            "--port",
+            "0",
+            "--profile",
+            "overloaded",
+        ])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .context("启动代理失败")?;
+    let result = (|| -> Result<()> {
+        let mut stdout = BufReader::new(child.stdout.take().context("获取代理输出失败")?);
+        let mut next_line = || -> Result<serde_json::Value> {
+            let mut line = String::new();
+            stdout.read_line(&mut line).context("读取代理输出失败")?;
+            serde_json::from_str(&line).with_context(|| format!("代理输出不是 JSON: {}", line))
+        };
+        let started = next_line()?;
+        let proxy_url = field(&started, "/result/base_url")
+            .as_str()
+            .context("代理未输出地址")?
+            .to_string();
+        if field(&started, "/result/target") != "overloaded" {
+            anyhow::bail!("代理目标不匹配: {}", started);
+        }
+
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        // 客户端的凭据被替换为目标配置的凭据，目标返回 529 时改用备用配置，SSE 原样转发
+        let mut response = agent
+            .post(&format!("{}/v1/messages", proxy_url))
+            .header("x-api-key", "placeholder")
+            .content_type("application/json")
+            .send(r#"{"stream":true}"#)
+            .context("请求代理失败")?;
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let text = response
+            .body_mut()
+            .read_to_string()
+            .context("读取响应失败")?;
+        if response.status().as_u16() != 200
+            || content_type != "text/event-stream"
+            || text != MOCK_SSE
+        {
+            anyhow::bail!(
+                "SSE 响应未原样转发: {} {} {:?}",
+                response.status(),
+                content_type,
+                text
+            );
+        }
+        let logged = next_line()?;
+        if field(&logged, "/result/profile") != "good"
+            || field(&logged, "/result/status") != 200
+            || field(&logged, "/result/skipped/0/profile") != "overloaded"
+            || field(&logged, "/result/skipped/0/reason") != "HTTP 529"
+        {
+            anyhow::bail!("请求记录不匹配: {}", logged);
+        }
+
+        // 切换目标后立即生效
+        let mut cmd = Command::new(cargo_bin!("cccrs"));
+        cmd.args(["proxy", "switch", "expired"]).assert().success();
+        let models = agent
+            .get(&format!("{}/v1/models", proxy_url))
+            .call()
+            .context("请求代理失败")?;
+        if models.status().as_u16() != 401 {
+            anyhow::bail!("切换目标后应使用 expired 的凭据: {}", models.status());
+        }
+        let switched = next_line()?;
+        if field(&switched, "/result/profile") != "expired" {
+            anyhow::bail!("请求记录不匹配: {}", switched);
+        }
+
+        // apiKeyHelper 生成的 Token 过期后，上游返回 401 时重新运行 apiKeyHelper 并重试
+        Command::new(cargo_bin!("cccrs"))
+            .args(["proxy", "switch", "rotating"])
+            .assert()
+            .success();
+        let stale = agent
+            .get(&format!("{}/v1/models", proxy_url))
+            .call()
+            .context("请求代理失败")?;
+        if stale.status().as_u16() != 401 {
+            anyhow::bail!("旧 Token 应被拒绝: {}", stale.status());
+        }
+        next_line()?;
+        std::fs::write(&token_path, "sk-valid").context("写入 Token 失败")?;
+        let rotated = agent
+            .get(&format!("{}/v1/models", proxy_url))
+            .call()
+            .context("请求代理失败")?;
+        if rotated.status().as_u16() != 200 {
+            anyhow::bail!("应使用新 Token 重试: {}", rotated.status());
+        }
+        let retried = next_line()?;
+        if field(&retried, "/result/profile") != "rotating"
+            || field(&retried, "/result/status") != 200
+        {
+            anyhow::bail!("请求记录不匹配: {}", retried);
+        }
+        Ok(())
+    })();
+    let _ = child.kill();
+    let _ = child.wait();
+    result?;
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&ccc_config_path).context("读取配置失败")?)
+            .context("解析配置失败")?;
+    if field(&saved, "/proxy_target") != "rotating" || field(&saved, "/current") != "expired" {
+        anyhow::bail!("代理目标记录不匹配: {}", saved);
+    }
+
+    let (missing_ok, missing) = run_json(&["--output", "json", "proxy", "switch", "missing"])?;
+    if missing_ok || field(&missing, "/error/code") != "profile_not_found" {
+        anyhow::bail!("切换到不存在的配置档案时错误输出不匹配: {}", missing);
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_hook_applies_dir_profile() -> Result<()> {
+    let (temp_dir, _settings_path, ccc_config_path) = setup_temp_home()?;
+    let config = serde_json::json!({
+        "profiles": {
+            "kimi": { "env": {
+                "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                "ANTHROPIC_AUTH_TOKEN": "sk-kimi"
+            } }
+        },
+        "current": null
+    });
+    std::fs::write(&ccc_config_path, config.to_string()).context("写入配置失败")?;
+    let client = temp_dir.path().join("client");
+    let nested = client.join("src");
+    let outside = temp_dir.path().join("outside");
+    std::fs::create_dir_all(&nested).context("创建目录失败")?;
+    std::fs::create_dir_all(&outside).context("创建目录失败")?;
+    std::fs::write(client.join(".cccrs.toml"), "profile = \"kimi\"\n").context("写入文件失败")?;
+    let dir_file = client.join(".cccrs.toml").to_string_lossy().into_owned();
+
+    let mut cmd = Command::new(cargo_bin!("cccrs"));
+    cmd.args(["hook", "zsh"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hook zsh --apply"));
+
+    // 生成脚本时指定的 --config-dir 写入 hook，hook 使用相同的配置
+    #[cfg(unix)]
+    {
+        let custom = temp_dir.path().join("custom");
+        std::fs::create_dir_all(&custom).context("创建目录失败")?;
+        let custom_config = serde_json::json!({
+            "profiles": {
+                "kimi": { "env": { "ANTHROPIC_AUTH_TOKEN": "sk-custom" } }
+            },
+            "current": null
+        });
+        std::fs::write(custom.join("cccrs-config.json"), custom_config.to_string())
+            .context("写入配置失败")?;
+        let generated = Command::new(cargo_bin!("cccrs"))
+            .arg("--config-dir")
+            .arg(&custom)
+            .args(["hook", "bash"])
+            .output()
+            .context("生成 hook 失败")?;
+        let script = String::from_utf8(generated.stdout).context("hook 输出不是 UTF-8")?;
+        let mut bash = Command::new("bash");
+        bash.arg("-c")
+            .arg(format!(
+                "{}\ncd '{}' && _cccrs_hook 2>/dev/null; printf '%s' \"$ANTHROPIC_AUTH_TOKEN\"",
+                script,
+                nested.display()
+            ))
+            .env_remove("CCCRS_DIR_FILE")
+            .env_remove("CCCRS_DIR_PROFILE")
+            .env_remove("CCCRS_DIR_KEYS")
+            .env_remove("ANTHROPIC_AUTH_TOKEN")
+            .assert()
+            .success()
+            .stdout("sk-custom");
+    }
+
+    // 进入目录时设置配置的环境变量，并记录状态和被覆盖的原值
+    let mut enter = Command::new(cargo_bin!("cccrs"));
+    enter
+        .args(["hook", "bash", "--apply"])
+        .current_dir(&nested)
+        .env_remove("CCCRS_DIR_FILE")
+        .env_remove("CCCRS_DIR_PROFILE")
+        .env_remove("CCCRS_DIR_KEYS")
+        .env_remove("ANTHROPIC_BASE_URL")
+        .env("ANTHROPIC_AUTH_TOKEN", "sk-user")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "export ANTHROPIC_BASE_URL='https://api.moonshot.cn/anthropic'",
+        ))
+        .stdout(predicate::str::contains(
+            "export ANTHROPIC_AUTH_TOKEN='sk-kimi'",
+        ))
+        .stdout(predicate::str::contains("export CCCRS_DIR_PROFILE='kimi'"))
+        .stdout(predicate::str::contains(
+            "export CCCRS_DIR_KEYS='ANTHROPIC_AUTH_TOKEN,ANTHROPIC_BASE_URL'",
+        ))
+        .stdout(predicate::str::contains(
+            "export CCCRS_DIR_PREV_ANTHROPIC_AUTH_TOKEN='sk-user'",
+        ))
+        .stdout(predicate::str::contains("CCCRS_DIR_PREV_ANTHROPIC_BASE_URL").not())
+        .stderr(predicate::str::contains("使用配置档案 kimi"));
+
+    // 目录配置未变化时不输出任何语句
+    let mut stay = Command::new(cargo_bin!("cccrs"));
+    stay.args(["hook", "bash", "--apply"])
+        .current_dir(&client)
+        .env("CCCRS_DIR_FILE", &dir_file)
+        .env("CCCRS_DIR_PROFILE", "kimi")
+        .env("CCCRS_DIR_KEYS", "ANTHROPIC_AUTH_TOKEN,ANTHROPIC_BASE_URL")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    // 离开目录时恢复进入前的值，进入前未设置的变量被清除
+    let mut leave = Command::new(cargo_bin!("cccrs"));
+    leave
+        .args(["hook", "fish", "--apply"])
+        .current_dir(&outside)
+        .env("CCCRS_DIR_FILE", &dir_file)
+        .env("CCCRS_DIR_PROFILE", "kimi")
+        .env("CCCRS_DIR_KEYS", "ANTHROPIC_AUTH_TOKEN,ANTHROPIC_BASE_URL")
+        .env("CCCRS_DIR_PREV_ANTHROPIC_AUTH_TOKEN", "sk-user")
+        .env("ANTHROPIC_AUTH_TOKEN", "sk-kimi")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "set -gx ANTHROPIC_AUTH_TOKEN 'sk-user'",
+        ))
+        .stdout(predicate::str::contains(
+            "set -e CCCRS_DIR_PREV_ANTHROPIC_AUTH_TOKEN",
+        ))
+        .stdout(predicate::str::contains("set -e ANTHROPIC_AUTH_TOKEN").not())
+        .stdout(predicate::str::contains("set -e ANTHROPIC_BASE_URL"))
+        .stdout(predicate::str::contains("set -e CCCRS_DIR_PROFILE"));
+
+    // 配置档案不存在时报错但仍记录状态，避免重复报错
+    std::fs::write(client.join(".cccrs"), "missing\n").context("写入文件失败")?;
+    let mut missing = Command::new(cargo_bin!("cccrs"));
+    missing
+        .args(["hook", "bash", "--apply"])
+        .current_dir(&client)
+        .env_remove("CCCRS_DIR_FILE")
+        .env_remove("CCCRS_DIR_PROFILE")
+        .env_remove("CCCRS_DIR_KEYS")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "export CCCRS_DIR_PROFILE='missing'",
+        ))
+        .stdout(predicate::str::contains("export CCCRS_DIR_KEYS=''"))
+        .stderr(predicate::str::contains("配置档案 'missing' 不存在"));
+    Ok(())
+}
+
+/// 运行命令并解析 stdout 中的 JSON
+fn run_json(args: &[&str]) -> Result<(bool, serde_json::Value)> {
+    let output = Command::new(cargo_bin!("cccrs"))
+        .args(args)
+        .output()
+        .context("运行 cccrs 失败")?;
+    let value = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("输出不是有效的 JSON: {:?}", args))?;
+    Ok((output.status.success(), value))
+}
+
+/// 按 JSON Pointer 获取字段，不存在时返回 `null`
+fn field<'a>(value: &'a serde_json::Value, pointer: &str) -> &'a serde_json::Value {
+    value.pointer(pointer).unwrap_or(&serde_json::Value::Null)
+}
+
+#[test]
+#[serial]
+fn test_json_output_mode() -> Result<()> {
+    let (_temp_dir, settings_path, ccc_config_path) = setup_temp_home()?;
+    create_initial_settings(&settings_path)?;
+
+    let config = r#"{
+        "profiles": {
+            "kimi": {
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/anthropic",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-kimi-key"
+                }
+            }
+        },
+        "current": null
+    }"#;
+    std::fs::write(&ccc_config_path, config).context("写入配置失败")?;
+
+    let (use_ok, used) = run_json(&["--output", "json", "use", "kimi"])?;
+    if !use_ok || field(&used, "/ok") != true || field(&used, "/result/profile") != "kimi" {
+        anyhow::bail!("use 输出不匹配: {}", used);
+    }
+    if !field(&used, "/result/backup_path").is_string() {
+        anyhow::bail!("use 输出缺少备份路径: {}", used);
+    }
+
+    let (list_ok, listed) = run_json(&["list", "--output", "json"])?;
+    let result = field(&listed, "/result");
+    if !list_ok
+        || field(result, "/active/name") != "kimi"
+        || field(result, "/active/source") != "detected"
+    {
+        anyhow::bail!("list 输出不匹配: {}", listed);
+    }
+    if *field(result, "/profiles/0/env_keys")
+        != serde_json::json!(["ANTHROPIC_AUTH_TOKEN", "ANTHROPIC_BASE_URL"])
+    {
+        anyhow::bail!("list 输出的环境变量不匹配: {}", listed);
+    }
+    if listed.to_string().contains("sk-kimi-key") {
+        anyhow::bail!("list 输出不应包含 API Key");
+    }
+
+    let (failed_ok, failed) = run_json(&["--output", "json", "use", "missing"])?;
+    if failed_ok
+        || field(&failed, "/ok") != false
+        || field(&failed, "/error/code") != "profile_not_found"
+    {
+        anyhow::bail!("错误输出不匹配: {}", failed);
+    }
+    Ok(())
+}